@@ -0,0 +1,285 @@
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndentStyle {
+    /// Indent with spaces
+    Spaces,
+    /// Indent with tabs, and align with spaces past the last tab stop
+    Tabs,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndOfLine {
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    Crlf,
+    /// `\r\n` on Windows and `\n` elsewhere
+    Native,
+    /// The line ending of the first line of the input, which may also be a lone `\r`
+    Auto,
+}
+
+impl EndOfLine {
+    /// Returns the line ending to use for the formatted output of `src`.
+    pub fn resolve(&self, src: &str) -> &'static str {
+        match self {
+            EndOfLine::Lf => "\n",
+            EndOfLine::Crlf => "\r\n",
+            EndOfLine::Native if cfg!(windows) => "\r\n",
+            EndOfLine::Native => "\n",
+            EndOfLine::Auto => match src.find(['\n', '\r']) {
+                Some(i) if src[i..].starts_with("\r\n") => "\r\n",
+                Some(i) if src[i..].starts_with('\r') => "\r",
+                _ => "\n",
+            },
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bom {
+    /// Keep a byte-order mark at the start of the input
+    Keep,
+    /// Remove a byte-order mark at the start of the input
+    Strip,
+}
+
+/// Options that control the formatted output.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The maximum width of a line.
+    pub max_width: usize,
+    /// The line ending of the formatted output.
+    pub end_of_line: EndOfLine,
+    /// Whether a byte-order mark at the start of the input is kept in the output.
+    pub bom: Bom,
+    /// Whether the output always ends with a line ending. Otherwise, a final line ending is only
+    /// kept if the input has one.
+    pub insert_final_newline: bool,
+    /// The most consecutive blank lines kept between top-level forms.
+    pub max_blank_lines: usize,
+    /// The most consecutive blank lines kept inside a form.
+    pub max_inner_blank_lines: usize,
+    /// The number of blank lines required before and after a top-level definition, such as a
+    /// `defn`. Takes precedence over `max_blank_lines`.
+    pub blank_lines_between_definitions: usize,
+    /// The number of columns taken up by characters of ambiguous East Asian width, 1 or 2.
+    pub ambiguous_width: usize,
+    /// Whether lines are indented with spaces or tabs.
+    pub indent_style: IndentStyle,
+    /// The number of columns a tab advances to, used for indentation with tabs and for measuring
+    /// lines containing tabs.
+    pub tab_width: usize,
+    /// How far the contents of a list are indented relative to its opening delimiter.
+    pub list_indent: usize,
+    /// How far the contents of a sequence are indented relative to its opening delimiter.
+    pub sequence_indent: usize,
+    /// How far the contents of a table are indented relative to its opening delimiter.
+    pub table_indent: usize,
+    /// Whether the values of table entries, bindings and clauses are aligned into a column.
+    pub align: bool,
+    /// The width of the widest key that is still aligned. A group containing a wider key is not
+    /// aligned.
+    pub align_max_key_width: usize,
+    /// Whether the trailing comments of consecutive lines are aligned into a column.
+    pub align_comments: bool,
+    /// The column aligned trailing comments start at or after.
+    pub comment_min_column: usize,
+    /// The most spaces added in front of a trailing comment to align it. A group of comments
+    /// that would need more is not aligned.
+    pub comment_max_padding: usize,
+    /// Whether comments are normalized to start with `;` after an expression, `;;` inside a
+    /// form and `;;;` at the top level, followed by a single space. Top-level headings with more
+    /// semicolons keep them.
+    pub normalize_comments: bool,
+    /// Whether runs of comments on their own lines are reflowed to fill the maximum width.
+    pub reflow_comments: bool,
+    /// Whether the continuation lines of docstrings are reindented when their form moves.
+    pub docstrings: bool,
+    /// Whether the paragraphs of docstrings are wrapped to the maximum width. Requires
+    /// `docstrings`, see [`Config::validate`].
+    pub wrap_docstrings: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_width: 100,
+            end_of_line: EndOfLine::Auto,
+            bom: Bom::Keep,
+            insert_final_newline: false,
+            max_blank_lines: 1,
+            max_inner_blank_lines: 1,
+            blank_lines_between_definitions: 0,
+            ambiguous_width: 1,
+            indent_style: IndentStyle::Spaces,
+            tab_width: 8,
+            list_indent: 2,
+            sequence_indent: 1,
+            table_indent: 1,
+            align: false,
+            align_max_key_width: 20,
+            align_comments: false,
+            comment_min_column: 0,
+            comment_max_padding: 20,
+            normalize_comments: false,
+            reflow_comments: false,
+            docstrings: false,
+            wrap_docstrings: false,
+        }
+    }
+}
+
+impl Config {
+    /// Checks that options which depend on each other are set together and that options have a
+    /// valid value, after the options of every source have been applied.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.wrap_docstrings && !self.docstrings {
+            return Err("`wrap_docstrings` requires `docstrings` to be enabled".to_string());
+        }
+        if !matches!(self.ambiguous_width, 1 | 2) {
+            return Err(format!(
+                "`ambiguous_width` must be 1 or 2, not {}",
+                self.ambiguous_width
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of columns `text` takes up when displayed, counting East Asian wide
+    /// characters as 2 columns and combining characters as none.
+    pub fn width(&self, text: &str) -> usize {
+        if self.ambiguous_width == 2 {
+            text.width_cjk()
+        } else {
+            text.width()
+        }
+    }
+
+    /// Returns the number of columns a character takes up when displayed.
+    pub fn char_width(&self, c: char) -> usize {
+        if self.ambiguous_width == 2 {
+            c.width_cjk().unwrap_or(0)
+        } else {
+            c.width().unwrap_or(0)
+        }
+    }
+
+    /// Returns the number of columns `text` takes up when displayed starting at `column`, with
+    /// tabs advancing to the next multiple of the tab width.
+    pub fn visual_width(&self, text: &str, column: usize) -> usize {
+        text.chars().fold(column, |col, c| match c {
+            '\t' => (col / self.tab_width + 1) * self.tab_width,
+            _ => col + self.char_width(c),
+        }) - column
+    }
+
+    /// Returns the whitespace that indents a line to `column`.
+    pub fn indentation(&self, column: usize) -> String {
+        match self.indent_style {
+            IndentStyle::Spaces => " ".repeat(column),
+            IndentStyle::Tabs => format!(
+                "{}{}",
+                "\t".repeat(column / self.tab_width),
+                " ".repeat(column % self.tab_width)
+            ),
+        }
+    }
+}
+
+/// The name of the configuration file looked up in the directory of a formatted file and its
+/// ancestors.
+pub const FILE_NAME: &str = "lispfmt.toml";
+
+/// Returns the configuration file closest to `dir`, if there is one.
+pub fn find_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// A set of options that override those of a [`Config`], read from a configuration file, an
+/// `.editorconfig` file or the command line. Options that are not set are left as they are.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Options {
+    pub max_width: Option<usize>,
+    pub end_of_line: Option<EndOfLine>,
+    pub bom: Option<Bom>,
+    pub insert_final_newline: Option<bool>,
+    pub max_blank_lines: Option<usize>,
+    pub max_inner_blank_lines: Option<usize>,
+    pub blank_lines_between_definitions: Option<usize>,
+    pub ambiguous_width: Option<usize>,
+    pub indent_style: Option<IndentStyle>,
+    pub tab_width: Option<NonZeroUsize>,
+    pub list_indent: Option<usize>,
+    pub sequence_indent: Option<usize>,
+    pub table_indent: Option<usize>,
+    pub align: Option<bool>,
+    pub align_max_key_width: Option<usize>,
+    pub align_comments: Option<bool>,
+    pub comment_min_column: Option<usize>,
+    pub comment_max_padding: Option<usize>,
+    pub normalize_comments: Option<bool>,
+    pub reflow_comments: Option<bool>,
+    pub docstrings: Option<bool>,
+    pub wrap_docstrings: Option<bool>,
+}
+
+impl Options {
+    /// Parses the contents of a configuration file.
+    pub fn from_toml(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Overrides the options of `config` that are set.
+    pub fn apply(&self, config: &mut Config) {
+        fn set<T: Copy>(option: Option<T>, value: &mut T) {
+            if let Some(option) = option {
+                *value = option;
+            }
+        }
+
+        set(self.max_width, &mut config.max_width);
+        set(self.end_of_line, &mut config.end_of_line);
+        set(self.bom, &mut config.bom);
+        set(self.insert_final_newline, &mut config.insert_final_newline);
+        set(self.max_blank_lines, &mut config.max_blank_lines);
+        set(
+            self.max_inner_blank_lines,
+            &mut config.max_inner_blank_lines,
+        );
+        set(
+            self.blank_lines_between_definitions,
+            &mut config.blank_lines_between_definitions,
+        );
+        set(self.ambiguous_width, &mut config.ambiguous_width);
+        set(self.indent_style, &mut config.indent_style);
+        set(self.tab_width.map(NonZeroUsize::get), &mut config.tab_width);
+        set(self.list_indent, &mut config.list_indent);
+        set(self.sequence_indent, &mut config.sequence_indent);
+        set(self.table_indent, &mut config.table_indent);
+        set(self.align, &mut config.align);
+        set(self.align_max_key_width, &mut config.align_max_key_width);
+        set(self.align_comments, &mut config.align_comments);
+        set(self.comment_min_column, &mut config.comment_min_column);
+        set(self.comment_max_padding, &mut config.comment_max_padding);
+        set(self.normalize_comments, &mut config.normalize_comments);
+        set(self.reflow_comments, &mut config.reflow_comments);
+        set(self.docstrings, &mut config.docstrings);
+        set(self.wrap_docstrings, &mut config.wrap_docstrings);
+    }
+}
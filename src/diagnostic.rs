@@ -0,0 +1,321 @@
+use ariadne::{Color, Label, Report, ReportKind, sources};
+use clap::ValueEnum;
+use serde_json::{Value, json};
+
+use crate::{
+    error_code::ErrorCode,
+    line_index::{Encoding, LineIndex},
+    node::Span,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+pub enum ErrorFormat {
+    /// Annotated source snippets for humans
+    Human,
+    /// One `file:line:column: severity[code]: message` line per diagnostic
+    Short,
+    /// One JSON object per line for each diagnostic
+    Json,
+    /// A single SARIF 2.1.0 log containing every diagnostic
+    Sarif,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub code: ErrorCode,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    /// Locations in other texts the diagnostic refers to, such as the formatted output.
+    pub related: Vec<Related>,
+    /// Further details printed after the diagnostic, such as a diff.
+    pub note: Option<String>,
+}
+
+/// A location in a text other than the file a diagnostic is reported in.
+#[derive(Clone, Debug)]
+pub struct Related {
+    /// What the text is, e.g. `formatted`.
+    pub name: String,
+    pub text: String,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(code: ErrorCode, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            code,
+            severity: Severity::Error,
+            message: message.into(),
+            span,
+            related: vec![],
+            note: None,
+        }
+    }
+
+    pub fn warning(code: ErrorCode, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            code,
+            severity: Severity::Warning,
+            message: message.into(),
+            span,
+            related: vec![],
+            note: None,
+        }
+    }
+
+    pub fn with_related(mut self, related: Related) -> Self {
+        self.related.push(related);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+}
+
+/// Writes diagnostics to stderr in the selected [`ErrorFormat`].
+pub struct Emitter {
+    format: ErrorFormat,
+    sarif_results: Vec<Value>,
+    sarif_rules: Vec<ErrorCode>,
+}
+
+impl Emitter {
+    pub fn new(format: ErrorFormat) -> Self {
+        Self {
+            format,
+            sarif_results: vec![],
+            sarif_rules: vec![],
+        }
+    }
+
+    pub fn emit(&mut self, file: &str, line_index: &LineIndex, diagnostic: &Diagnostic) {
+        // Lines and columns are reported 1-based, with columns counted in chars except for SARIF,
+        // which counts UTF-16 code units by default
+        let encoding = match self.format {
+            ErrorFormat::Sarif => Encoding::Utf16,
+            _ => Encoding::Char,
+        };
+        let start = line_index.line_col(diagnostic.span.start, encoding);
+        let end = line_index.line_col(diagnostic.span.end, encoding);
+
+        match self.format {
+            ErrorFormat::Human => {
+                let mut texts = vec![(file.to_string(), line_index.text())];
+                texts.extend(
+                    diagnostic
+                        .related
+                        .iter()
+                        .map(|related| (related_file(file, related), related.text.as_str())),
+                );
+                build_report(file, diagnostic)
+                    .eprint(sources(texts))
+                    .unwrap();
+                if let Some(note) = &diagnostic.note {
+                    eprint!("{}", note);
+                }
+            }
+            ErrorFormat::Short => {
+                eprintln!(
+                    "{}:{}:{}: {}[{}]: {}",
+                    file,
+                    start.line + 1,
+                    start.column + 1,
+                    diagnostic.severity.name(),
+                    diagnostic.code,
+                    diagnostic.message
+                );
+                for related in &diagnostic.related {
+                    let start =
+                        LineIndex::new(&related.text).line_col(related.span.start, encoding);
+                    eprintln!(
+                        "{}:{}:{}: note: {}",
+                        related_file(file, related),
+                        start.line + 1,
+                        start.column + 1,
+                        related.message
+                    );
+                }
+                if let Some(note) = &diagnostic.note {
+                    eprint!("{}", note);
+                }
+            }
+            ErrorFormat::Json => eprintln!(
+                "{}",
+                json!({
+                    "file": file,
+                    "code": diagnostic.code.as_str(),
+                    "severity": diagnostic.severity.name(),
+                    "message": diagnostic.message,
+                    "span": {
+                        "start": diagnostic.span.start,
+                        "end": diagnostic.span.end,
+                    },
+                    "start": { "line": start.line + 1, "column": start.column + 1 },
+                    "end": { "line": end.line + 1, "column": end.column + 1 },
+                    "related": diagnostic
+                        .related
+                        .iter()
+                        .map(|related| {
+                            let line_index = LineIndex::new(&related.text);
+                            let start = line_index.line_col(related.span.start, encoding);
+                            let end = line_index.line_col(related.span.end, encoding);
+                            json!({
+                                "file": related_file(file, related),
+                                "message": related.message,
+                                "span": {
+                                    "start": related.span.start,
+                                    "end": related.span.end,
+                                },
+                                "start": { "line": start.line + 1, "column": start.column + 1 },
+                                "end": { "line": end.line + 1, "column": end.column + 1 },
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                    "note": diagnostic.note,
+                })
+            ),
+            ErrorFormat::Sarif => {
+                if !self.sarif_rules.contains(&diagnostic.code) {
+                    self.sarif_rules.push(diagnostic.code);
+                }
+
+                let text = match &diagnostic.note {
+                    Some(note) => format!("{}\n\n{}", diagnostic.message, note),
+                    None => diagnostic.message.clone(),
+                };
+                let related_locations = diagnostic
+                    .related
+                    .iter()
+                    .enumerate()
+                    .map(|(id, related)| {
+                        let line_index = LineIndex::new(&related.text);
+                        let start = line_index.line_col(related.span.start, encoding);
+                        let end = line_index.line_col(related.span.end, encoding);
+                        json!({
+                            "id": id,
+                            "message": { "text": related.message },
+                            "physicalLocation": {
+                                "artifactLocation": { "uri": related_file(file, related) },
+                                "region": {
+                                    "startLine": start.line + 1,
+                                    "startColumn": start.column + 1,
+                                    "endLine": end.line + 1,
+                                    "endColumn": end.column + 1,
+                                    "byteOffset": related.span.start,
+                                    "byteLength": related.span.end - related.span.start,
+                                },
+                            },
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                self.sarif_results.push(json!({
+                    "ruleId": diagnostic.code.as_str(),
+                    "level": diagnostic.severity.name(),
+                    "message": { "text": text },
+                    "relatedLocations": related_locations,
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": file },
+                            "region": {
+                                "startLine": start.line + 1,
+                                "startColumn": start.column + 1,
+                                "endLine": end.line + 1,
+                                "endColumn": end.column + 1,
+                                "byteOffset": diagnostic.span.start,
+                                "byteLength": diagnostic.span.end - diagnostic.span.start,
+                            },
+                        },
+                    }],
+                }));
+            }
+        }
+    }
+
+    /// Flushes diagnostics that can only be written once every file has been processed.
+    pub fn finish(self) {
+        if self.format != ErrorFormat::Sarif {
+            return;
+        }
+
+        let rules = self
+            .sarif_rules
+            .iter()
+            .map(|code| {
+                json!({
+                    "id": code.as_str(),
+                    "shortDescription": { "text": code.title() },
+                    "fullDescription": { "text": code.explanation() },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let log = json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    },
+                },
+                "results": self.sarif_results,
+            }],
+        });
+
+        eprintln!("{}", serde_json::to_string_pretty(&log).unwrap());
+    }
+}
+
+fn build_report<'a>(
+    file: &'a str,
+    diagnostic: &'a Diagnostic,
+) -> Report<'a, (String, std::ops::Range<usize>)> {
+    let (kind, color) = match diagnostic.severity {
+        Severity::Error => (ReportKind::Error, Color::Red),
+        Severity::Warning => (ReportKind::Warning, Color::Yellow),
+    };
+    let span = (file.to_string(), diagnostic.span.into_range());
+
+    Report::build(kind, span.clone())
+        .with_config(ariadne::Config::new().with_index_type(ariadne::IndexType::Byte))
+        .with_code(diagnostic.code)
+        .with_message(&diagnostic.message)
+        .with_label(
+            Label::new(span)
+                .with_message(&diagnostic.message)
+                .with_color(color),
+        )
+        .with_labels(diagnostic.related.iter().map(|related| {
+            Label::new((related_file(file, related), related.span.into_range()))
+                .with_message(&related.message)
+                .with_color(Color::Blue)
+        }))
+        .finish()
+}
+
+/// Returns the name a related text is shown under, such as `file.clj (formatted)`.
+fn related_file(file: &str, related: &Related) -> String {
+    format!("{} ({})", file, related.name)
+}
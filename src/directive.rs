@@ -0,0 +1,162 @@
+//! Comments that control the formatter.
+
+use crate::{diagnostic::Diagnostic, error_code::ErrorCode, kind::SyntaxKind, node::SyntaxElement};
+
+/// The optional rules that can be disabled for a single form with `; lispfmt-ignore <rule>`, named
+/// like the options that enable them.
+pub const RULES: &[&str] = &[
+    "align",
+    "align_comments",
+    "normalize_comments",
+    "reflow_comments",
+    "docstrings",
+];
+
+/// A region of the children of a container, from a `lispfmt-off` comment to a `lispfmt-on`
+/// comment, that is kept as written.
+pub struct Region<'a> {
+    pub off: &'a SyntaxElement<'a>,
+    pub on: &'a SyntaxElement<'a>,
+}
+
+fn is_marker(elem: &SyntaxElement, marker: &str) -> bool {
+    *elem.kind() == SyntaxKind::Comment && elem.text().trim_start_matches(';').trim() == marker
+}
+
+/// Returns whether formatting is disabled for the whole file by a `; lispfmt-ignore-file` comment
+/// before its first expression.
+pub fn ignores_file(root: &SyntaxElement) -> bool {
+    root.children()
+        .take_while(|e| e.kind().is_trivia())
+        .any(|e| is_marker(e, "lispfmt-ignore-file"))
+}
+
+/// Returns the names of the rules of a `; lispfmt-ignore [<rule>...] [-- <reason>]` comment, or
+/// `None` for other comments. Everything after `--` is a free-text reason.
+fn ignore_directive<'a>(comment: &'a SyntaxElement) -> Option<Vec<&'a str>> {
+    if *comment.kind() != SyntaxKind::Comment {
+        return None;
+    }
+
+    let mut words = comment.text().trim_start_matches(';').split_whitespace();
+    (words.next() == Some("lispfmt-ignore"))
+        .then(|| words.take_while(|word| *word != "--").collect())
+}
+
+/// Returns whether a comment is a `; lispfmt-ignore` comment without rules, which keeps the
+/// expression after it as written.
+pub fn ignores_expr(comment: &SyntaxElement) -> bool {
+    ignore_directive(comment).is_some_and(|rules| rules.is_empty())
+}
+
+/// Returns the names of the rules disabled by a `; lispfmt-ignore <rule>...` comment.
+pub fn ignored_rules<'a>(comment: &'a SyntaxElement) -> Vec<&'a str> {
+    ignore_directive(comment).unwrap_or_default()
+}
+
+/// Pairs the `lispfmt-off` and `lispfmt-on` comments among the children of a container.
+///
+/// Returns the matched regions and the markers without a counterpart. Markers inside a region
+/// other than the one ending it are part of the region.
+pub fn regions<'a>(
+    children: impl Iterator<Item = &'a SyntaxElement<'a>>,
+) -> (Vec<Region<'a>>, Vec<&'a SyntaxElement<'a>>) {
+    let mut regions = vec![];
+    let mut unmatched = vec![];
+    let mut off = None;
+
+    for child in children {
+        match off {
+            None if is_marker(child, "lispfmt-off") => off = Some(child),
+            None if is_marker(child, "lispfmt-on") => unmatched.push(child),
+            Some(start) if is_marker(child, "lispfmt-on") => {
+                regions.push(Region {
+                    off: start,
+                    on: child,
+                });
+                off = None;
+            }
+            _ => (),
+        }
+    }
+
+    unmatched.extend(off);
+    (regions, unmatched)
+}
+
+/// Returns the expression the `lispfmt-ignore` comment at `index` among the expressions and trivia
+/// of a container applies to: the expression it follows on the same line, or otherwise the next
+/// expression.
+fn directive_target<'a>(
+    children: &[&'a SyntaxElement<'a>],
+    index: usize,
+) -> Option<&'a SyntaxElement<'a>> {
+    let previous = children[..index]
+        .iter()
+        .rev()
+        .find(|e| *e.kind() != SyntaxKind::Space)
+        .filter(|e| !e.kind().is_trivia());
+    let next = || children[index + 1..].iter().find(|e| !e.kind().is_trivia());
+
+    previous.or_else(next).copied()
+}
+
+/// Returns warnings about formatter comments that have no effect.
+pub fn warnings(root: &SyntaxElement) -> Vec<Diagnostic> {
+    let mut warnings = vec![];
+    collect_warnings(root, &mut warnings);
+    warnings.sort_by_key(|d| d.span.start);
+    warnings
+}
+
+fn collect_warnings(elem: &SyntaxElement, warnings: &mut Vec<Diagnostic>) {
+    let (_, unmatched) = regions(elem.children());
+
+    let unknown_rules = elem.children().flat_map(|comment| {
+        ignored_rules(comment)
+            .into_iter()
+            .filter(|rule| !RULES.contains(rule))
+            .map(|rule| {
+                let message = format!("unknown rule `{}`", rule);
+                Diagnostic::warning(ErrorCode::UnknownRule, message, comment.span())
+            })
+    });
+    warnings.extend(unknown_rules);
+
+    let children = elem.children().collect::<Vec<_>>();
+    let children = match elem.kind() {
+        SyntaxKind::List | SyntaxKind::Sequence | SyntaxKind::Table => {
+            &children[1..children.len() - 1]
+        }
+        _ => &children[..],
+    };
+    for (i, comment) in children.iter().enumerate() {
+        if ignore_directive(comment).is_none() {
+            continue;
+        }
+
+        let message = match directive_target(children, i) {
+            Some(SyntaxElement::Node(_)) => continue,
+            Some(token) => format!("`lispfmt-ignore` has no effect on `{}`", token.text()),
+            None => "`lispfmt-ignore` is not followed by an expression".to_string(),
+        };
+        warnings.push(Diagnostic::warning(
+            ErrorCode::UnusedDirective,
+            message,
+            comment.span(),
+        ));
+    }
+
+    warnings.extend(unmatched.into_iter().map(|marker| {
+        let message = if is_marker(marker, "lispfmt-off") {
+            "`lispfmt-off` without a matching `lispfmt-on`"
+        } else {
+            "`lispfmt-on` without a matching `lispfmt-off`"
+        };
+        Diagnostic::warning(ErrorCode::UnmatchedMarker, message, marker.span())
+    }));
+
+    for child in elem.children() {
+        collect_warnings(child, warnings);
+    }
+}
@@ -1,42 +1,123 @@
+use std::{borrow::Cow, ops::RangeInclusive};
+
 use pretty::{Arena, Doc, DocAllocator, DocBuilder};
 
-use crate::{doc_ext::DocExt, kind::SyntaxKind, node::SyntaxElement, peekable_ext::PeekableExt};
+use crate::{
+    config::Config,
+    directive::{self, Region},
+    doc_ext::DocExt,
+    docstring, forms,
+    kind::SyntaxKind,
+    line_index::LineIndex,
+    node::SyntaxElement,
+    peekable_ext::PeekableExt,
+};
+
+/// Information about parts of a document used when rendering it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Annotation {
+    /// A comment following an expression on the same line, and whether it may be aligned with the
+    /// trailing comments of the lines around it.
+    TrailingComment { align: bool },
+    /// A comment on its own line, and whether it may be reflowed with the comments around it.
+    LineComment { reflow: bool },
+    /// Text that must be copied to the output byte for byte, such as a string.
+    Verbatim,
+    /// Lines copied from the source byte for byte, starting with the indentation of the first,
+    /// which replaces the indentation of the line they start on.
+    Kept,
+    /// An expression kept as written, whose lines after the first keep their indentation.
+    Ignored,
+}
 
-pub type ArenaDoc<'a> = DocBuilder<'a, Arena<'a>>;
+pub type DocArena<'a> = Arena<'a, Annotation>;
+pub type ArenaDoc<'a> = DocBuilder<'a, DocArena<'a>, Annotation>;
 
-impl<'src> SyntaxElement<'src> {
-    pub fn to_doc(&'src self, arena: &'src Arena<'src>) -> ArenaDoc<'src> {
-        match self.kind() {
-            SyntaxKind::Root => convert_root(arena, self),
-            SyntaxKind::List => convert_list_like(arena, self, 2, true),
-            SyntaxKind::Sequence => {
-                let [_open, exprs @ .., _close] = &self.children().collect::<Vec<_>>()[..] else {
-                    panic!("Container is missing an opening or closing delimiter.");
-                };
+/// What converting a syntax tree into a document needs besides the tree itself.
+pub struct Context<'a> {
+    pub config: &'a Config,
+    /// The source text the tree was parsed from.
+    pub line_index: &'a LineIndex<'a>,
+}
 
-                let mut non_trivia = exprs.iter().filter(|e| !e.kind().is_trivia());
-                let heterogeneous = match non_trivia.next() {
-                    Some(first) => !non_trivia.all(|e| e.kind() == first.kind()),
-                    None => false,
-                };
+impl<'a> Context<'a> {
+    /// Returns the column of a byte offset in the source text as displayed.
+    fn source_column(&self, offset: usize) -> usize {
+        self.config
+            .visual_width(self.line_index.line_prefix(offset), 0)
+    }
+
+    fn with_config<'b>(&'b self, config: &'b Config) -> Context<'b> {
+        Context {
+            config,
+            line_index: self.line_index,
+        }
+    }
+}
+
+/// Returns the configuration for an expression surrounded by `trivia` if it contains
+/// `; lispfmt-ignore <rule>` comments, with those rules turned off.
+fn expr_config<'a>(
+    trivia: impl IntoIterator<Item = &'a SyntaxElement<'a>>,
+    config: &Config,
+) -> Option<Config> {
+    let rules = trivia
+        .into_iter()
+        .flat_map(|trivia| directive::ignored_rules(trivia))
+        .collect::<Vec<_>>();
+
+    if rules.is_empty() {
+        return None;
+    }
+
+    let mut config = config.clone();
+    for rule in rules {
+        match rule {
+            "align" => config.align = false,
+            "align_comments" => config.align_comments = false,
+            "normalize_comments" => config.normalize_comments = false,
+            "reflow_comments" => config.reflow_comments = false,
+            "docstrings" => config.docstrings = false,
+            _ => (),
+        }
+    }
+
+    Some(config)
+}
+
+/// Returns how far the contents of a container are indented relative to its opening delimiter when
+/// it is broken onto multiple lines.
+pub(crate) fn container_indent(kind: &SyntaxKind, config: &Config) -> Option<isize> {
+    let indent = match kind {
+        SyntaxKind::List => config.list_indent,
+        SyntaxKind::Sequence => config.sequence_indent,
+        SyntaxKind::Table => config.table_indent,
+        _ => return None,
+    };
+
+    Some(indent as isize)
+}
 
-                let doc = convert_list_like(arena, self, 1, heterogeneous);
-                if heterogeneous { doc } else { doc.group() }
+impl<'src> SyntaxElement<'src> {
+    pub fn to_doc(&'src self, arena: &'src DocArena<'src>, ctx: &Context) -> ArenaDoc<'src> {
+        match self.kind() {
+            SyntaxKind::Root => convert_root(arena, self, ctx),
+            SyntaxKind::List => {
+                let padding = alignment(arena, self, ctx, false);
+                convert_list_like(arena, self, ctx, true, &padding)
+            }
+            SyntaxKind::Sequence => convert_sequence(arena, self, ctx, false),
+            SyntaxKind::Table => {
+                let padding = alignment(arena, self, ctx, false);
+                convert_list_like(arena, self, ctx, false, &padding).group()
             }
-            SyntaxKind::Table => convert_list_like(arena, self, 1, false).group(),
 
-            // FIXME: Handle trivia between pair
-            SyntaxKind::Pair => arena.intersperse(
-                self.children()
-                    .filter(|e| !e.kind().is_trivia())
-                    .map(|expr| expr.to_doc(arena)),
-                arena.space(),
-            ),
+            SyntaxKind::Pair => convert_pair(arena, self, ctx, 0),
 
             // A prefixed cannot contain any trivia between its children
             SyntaxKind::Prefixed => self
                 .children()
-                .fold(arena.nil(), |doc, expr| doc.append(expr.to_doc(arena))),
+                .fold(arena.nil(), |doc, expr| doc.append(expr.to_doc(arena, ctx))),
 
             SyntaxKind::LParen
             | SyntaxKind::RParen
@@ -46,13 +127,16 @@ impl<'src> SyntaxElement<'src> {
             | SyntaxKind::RBracket
             | SyntaxKind::Symbol
             | SyntaxKind::Number
-            | SyntaxKind::String
             | SyntaxKind::Keyword
             | SyntaxKind::Boolean
             | SyntaxKind::Prefix
-            | SyntaxKind::End => arena.text(self.text()),
+            | SyntaxKind::End => display_text(arena, self.text(), ctx.config),
+
+            SyntaxKind::String => {
+                display_text(arena, self.text(), ctx.config).annotate(Annotation::Verbatim)
+            }
 
-            SyntaxKind::HashDirective => arena.text(self.text().trim_end()),
+            SyntaxKind::HashDirective => display_text(arena, self.text().trim_end(), ctx.config),
 
             SyntaxKind::Newline | SyntaxKind::Space | SyntaxKind::Comment => {
                 unreachable!("Trivia should not be handled through `to_doc`.")
@@ -61,6 +145,27 @@ impl<'src> SyntaxElement<'src> {
     }
 }
 
+/// Allocates a text document measured by its display width, with characters of ambiguous width
+/// taking up the configured number of columns.
+fn display_text<'src>(
+    arena: &'src DocArena<'src>,
+    text: impl Into<Cow<'src, str>>,
+    config: &Config,
+) -> ArenaDoc<'src> {
+    let text = text.into();
+    if config.ambiguous_width == 1 || text.is_ascii() {
+        // `pretty` already measures text by its display width with narrow ambiguous characters
+        return arena.text(text);
+    }
+
+    let width = config.width(&text);
+    let doc = match text {
+        Cow::Borrowed(text) => Doc::BorrowedText(text),
+        Cow::Owned(text) => Doc::OwnedText(text.into()),
+    };
+    DocBuilder(arena, Doc::RenderLen(width, arena.alloc(doc)).into())
+}
+
 fn is_leading_trivia(kind: &SyntaxKind) -> bool {
     matches!(
         kind,
@@ -72,46 +177,432 @@ fn is_trailing_trivia(kind: &SyntaxKind) -> bool {
     matches!(kind, SyntaxKind::Space | SyntaxKind::Comment)
 }
 
-fn is_ignore_comment<'a>(trivia: &'a SyntaxElement<'a>) -> bool {
-    trivia.kind() == &SyntaxKind::Comment
-        && trivia.text().trim_start_matches(";").trim() == "lispfmt-ignore"
+/// Returns the symbol at the head of a list.
+fn head_symbol<'a>(elem: &'a SyntaxElement<'a>) -> Option<&'a str> {
+    if *elem.kind() != SyntaxKind::List {
+        return None;
+    }
+
+    elem.children()
+        .skip(1)
+        .find(|e| !e.kind().is_trivia())
+        .filter(|e| *e.kind() == SyntaxKind::Symbol)
+        .map(|e| e.text())
+}
+
+/// Returns the expressions of a container along with the trivia preceding each of them.
+fn entries<'a>(
+    elem: &'a SyntaxElement<'a>,
+) -> Vec<(Vec<&'a SyntaxElement<'a>>, &'a SyntaxElement<'a>)> {
+    let children = elem.children().collect::<Vec<_>>();
+    let mut entries = vec![];
+    let mut trivia = vec![];
+
+    for child in &children[1..children.len() - 1] {
+        if child.kind().is_trivia() {
+            trivia.push(*child);
+        } else {
+            entries.push((std::mem::take(&mut trivia), *child));
+        }
+    }
+
+    entries
+}
+
+/// Returns the width of an expression when rendered on a single line, or `None` if it spans
+/// multiple lines.
+fn flat_width<'src>(
+    arena: &'src DocArena<'src>,
+    expr: &'src SyntaxElement<'src>,
+    ctx: &Context,
+) -> Option<usize> {
+    let text = expr
+        .to_doc(arena, ctx)
+        .pretty(ctx.config.max_width)
+        .to_string();
+    (!text.contains('\n')).then(|| ctx.config.width(&text))
+}
+
+/// Returns the padding to insert after the key of every expression in a container so that the
+/// values of its table entries, bindings or clauses line up.
+///
+/// A group is not aligned at all if one of its keys is too wide, spans multiple lines, or is
+/// separated from its value by a comment or a line break.
+fn alignment<'src>(
+    arena: &'src DocArena<'src>,
+    elem: &'src SyntaxElement<'src>,
+    ctx: &Context,
+    bindings: bool,
+) -> Vec<usize> {
+    let entries = entries(elem);
+    let mut padding = vec![0; entries.len()];
+
+    if !ctx.config.align {
+        return padding;
+    }
+
+    let has_newline =
+        |trivia: &[&SyntaxElement]| trivia.iter().any(|t| *t.kind() == SyntaxKind::Newline);
+    let has_comment =
+        |trivia: &[&SyntaxElement]| trivia.iter().any(|t| *t.kind() == SyntaxKind::Comment);
+
+    let mut keys = vec![];
+    match elem.kind() {
+        SyntaxKind::Table => {
+            for (i, (_, expr)) in entries.iter().enumerate() {
+                if *expr.kind() != SyntaxKind::Pair {
+                    continue;
+                }
+                if expr.children().any(|e| *e.kind() == SyntaxKind::Comment) {
+                    return padding;
+                }
+                let key = expr.children().next().expect("Pair should have a key.");
+                keys.push((i, key));
+            }
+        }
+        SyntaxKind::Sequence | SyntaxKind::List => {
+            let offset = match head_symbol(elem) {
+                _ if bindings => 0,
+                Some(name) => match forms::clause_offset(name) {
+                    Some(offset) => offset,
+                    None => return padding,
+                },
+                None => return padding,
+            };
+
+            for (i, pair) in entries[offset.min(entries.len())..]
+                .chunks_exact(2)
+                .enumerate()
+            {
+                let [(key_trivia, key), (value_trivia, _)] = pair else {
+                    unreachable!();
+                };
+                let first = bindings && i == 0;
+
+                if !first && !has_newline(key_trivia)
+                    || has_newline(value_trivia)
+                    || has_comment(value_trivia)
+                {
+                    return padding;
+                }
+                keys.push((offset + i * 2, *key));
+            }
+        }
+        _ => return padding,
+    }
+
+    if keys.len() < 2 {
+        return padding;
+    }
+
+    let mut widths = vec![];
+    for (_, key) in &keys {
+        match flat_width(arena, key, ctx) {
+            Some(width) if width <= ctx.config.align_max_key_width => widths.push(width),
+            _ => return padding,
+        }
+    }
+
+    let max = widths.iter().copied().max().unwrap_or(0);
+    for ((i, _), width) in keys.iter().zip(widths) {
+        padding[*i] = max - width;
+    }
+
+    padding
+}
+
+/// Returns the index of the docstring of a definition, counting its head.
+///
+/// A string is only a docstring if the expressions before it match one of the positions of the
+/// form, and it is not the last expression, which would be the value or body of the form.
+fn docstring_index(elem: &SyntaxElement, config: &Config) -> Option<usize> {
+    if !config.docstrings {
+        return None;
+    }
+
+    let positions = forms::docstring_positions(head_symbol(elem)?);
+    let entries = entries(elem);
+    positions.iter().find_map(|preceding| {
+        let index = preceding.len() + 1;
+        let matches = index + 1 < entries.len()
+            && *entries[index].1.kind() == SyntaxKind::String
+            && entries[1..index]
+                .iter()
+                .zip(preceding.iter())
+                .all(|((_, expr), kind)| expr.kind() == kind);
+        matches.then_some(index)
+    })
+}
+
+/// Returns the docstring of a definition, if docstrings are formatted.
+pub(crate) fn docstring<'a>(
+    elem: &'a SyntaxElement<'a>,
+    config: &Config,
+) -> Option<&'a SyntaxElement<'a>> {
+    docstring_index(elem, config).map(|index| entries(elem)[index].1)
+}
+
+fn convert_docstring<'src>(
+    arena: &'src DocArena<'src>,
+    string: &'src SyntaxElement<'src>,
+    ctx: &Context,
+) -> ArenaDoc<'src> {
+    let text = string.text();
+    let original_column = ctx.source_column(string.span().start);
+    let config = ctx.config.clone();
+
+    arena.column(move |column| {
+        let text = docstring::reindent(text, original_column, column, &config);
+        display_text(arena, text, &config)
+            .annotate(Annotation::Verbatim)
+            .into_doc()
+    })
+}
+
+/// Returns the tokens of an element in order.
+fn tokens<'a>(elem: &'a SyntaxElement<'a>) -> Vec<&'a SyntaxElement<'a>> {
+    match elem {
+        SyntaxElement::Token(_) => vec![elem],
+        SyntaxElement::Node(_) => elem.children().flat_map(tokens).collect(),
+    }
+}
+
+/// Converts an expression that is kept as written.
+///
+/// Its line breaks are hard line breaks, so they break the enclosing groups, and its lines keep
+/// their original column relative to the first line. Strings are copied as is.
+fn convert_ignored<'src>(
+    arena: &'src DocArena<'src>,
+    expr: &'src SyntaxElement<'src>,
+    ctx: &Context,
+) -> ArenaDoc<'src> {
+    let column = ctx.source_column(expr.span().start);
+    let mut doc = arena.nil();
+    let mut line_start = false;
+
+    for token in tokens(expr) {
+        let text = token.text();
+        let token_doc = match token.kind() {
+            SyntaxKind::Newline => arena.hardline(),
+            SyntaxKind::Space if line_start => {
+                let width = ctx.config.visual_width(text, 0);
+                arena.text(" ".repeat(width.saturating_sub(column)))
+            }
+            SyntaxKind::String => {
+                display_text(arena, text, ctx.config).annotate(Annotation::Verbatim)
+            }
+            SyntaxKind::Comment | SyntaxKind::HashDirective => {
+                display_text(arena, text.trim_end(), ctx.config)
+            }
+            _ => display_text(arena, text, ctx.config),
+        };
+        doc = doc.append(token_doc);
+        line_start = *token.kind() == SyntaxKind::Newline;
+    }
+
+    doc.align().annotate(Annotation::Ignored)
+}
+
+fn region_at<'a, 'r>(regions: &'r [Region<'a>], elem: &SyntaxElement) -> Option<&'r Region<'a>> {
+    regions.iter().find(|region| std::ptr::eq(region.off, elem))
+}
+
+/// Skips the children of a container up to the end of `region` and converts the region, along with
+/// the number of expressions in it.
+///
+/// The source is copied byte for byte from the start of the line of the `lispfmt-off` comment, or
+/// from the comment if code precedes it, to the end of the `lispfmt-on` comment.
+fn convert_region<'src>(
+    arena: &'src DocArena<'src>,
+    iter: &mut impl Iterator<Item = &'src SyntaxElement<'src>>,
+    region: &Region<'src>,
+    ctx: &Context,
+) -> (ArenaDoc<'src>, usize) {
+    let mut elements = vec![region.off];
+    elements.extend(iter.take_while(|e| !std::ptr::eq(*e, region.on)));
+    elements.push(region.on);
+
+    let exprs = elements.iter().filter(|e| !e.kind().is_trivia()).count();
+
+    let mut start = region.off.span().start;
+    let prefix = ctx.line_index.line_prefix(start);
+    if prefix.trim().is_empty() {
+        start -= prefix.len();
+    }
+    let end = region.on.span().start + region.on.text().trim_end().len();
+    let text = ctx.line_index.text()[start..end].to_string();
+
+    let doc = arena
+        .text(text)
+        .annotate(Annotation::Verbatim)
+        .annotate(Annotation::Kept)
+        .append(arena.break_group());
+
+    (doc, exprs)
+}
+
+/// Returns spaces that are only emitted when the enclosing group is broken.
+fn align_padding<'src>(arena: &'src DocArena<'src>, padding: usize) -> ArenaDoc<'src> {
+    if padding == 0 {
+        return arena.nil();
+    }
+
+    arena.text(" ".repeat(padding)).flat_alt(arena.nil())
+}
+
+fn convert_sequence<'src>(
+    arena: &'src DocArena<'src>,
+    elem: &'src SyntaxElement<'src>,
+    ctx: &Context,
+    bindings: bool,
+) -> ArenaDoc<'src> {
+    let [_open, exprs @ .., _close] = &elem.children().collect::<Vec<_>>()[..] else {
+        panic!("Container is missing an opening or closing delimiter.");
+    };
+
+    let mut non_trivia = exprs.iter().filter(|e| !e.kind().is_trivia());
+    let heterogeneous = match non_trivia.next() {
+        Some(first) => !non_trivia.all(|e| e.kind() == first.kind()),
+        None => false,
+    };
+
+    let padding = if heterogeneous {
+        alignment(arena, elem, ctx, bindings)
+    } else {
+        vec![]
+    };
+
+    let doc = convert_list_like(arena, elem, ctx, heterogeneous, &padding);
+    if heterogeneous { doc } else { doc.group() }
+}
+
+// FIXME: Handle trivia between pair
+fn convert_pair<'src>(
+    arena: &'src DocArena<'src>,
+    pair: &'src SyntaxElement<'src>,
+    ctx: &Context,
+    padding: usize,
+) -> ArenaDoc<'src> {
+    let mut exprs = pair.children().filter(|e| !e.kind().is_trivia());
+    let key = exprs.next().expect("Pair should have a key.");
+
+    let mut doc = key.to_doc(arena, ctx).append(align_padding(arena, padding));
+    for expr in exprs {
+        doc = doc.append(arena.space()).append(expr.to_doc(arena, ctx));
+    }
+
+    doc
+}
+
+/// Where a comment appears, which decides how many semicolons it starts with when normalized.
+#[derive(Copy, Clone)]
+enum CommentPosition {
+    /// After an expression on the same line.
+    Trailing,
+    /// On its own line inside a form.
+    Inner,
+    /// On its own line at the top level.
+    TopLevel,
+}
+
+fn comment<'src>(
+    arena: &'src DocArena<'src>,
+    comment: &'src SyntaxElement<'src>,
+    position: CommentPosition,
+    ctx: &Context,
+) -> ArenaDoc<'src> {
+    let text = comment.text().trim_end();
+    let content = text.trim_start_matches(';');
+
+    // Autoload cookies and similar magic comments are kept as written
+    if !ctx.config.normalize_comments || content.starts_with("###") {
+        return display_text(arena, text, ctx.config);
+    }
+
+    let semicolons = match position {
+        CommentPosition::Trailing => ";",
+        CommentPosition::Inner => ";;",
+        // Headings such as `;;;;` keep their level
+        CommentPosition::TopLevel if text.len() - content.len() > 3 => {
+            &text[..text.len() - content.len()]
+        }
+        CommentPosition::TopLevel => ";;;",
+    };
+
+    match content.trim_start() {
+        "" => arena.text(semicolons),
+        content => display_text(arena, format!("{} {}", semicolons, content), ctx.config),
+    }
+}
+
+fn trailing_comment<'src>(
+    arena: &'src DocArena<'src>,
+    trivia: &'src SyntaxElement<'src>,
+    ctx: &Context,
+) -> ArenaDoc<'src> {
+    comment(arena, trivia, CommentPosition::Trailing, ctx).annotate(Annotation::TrailingComment {
+        align: ctx.config.align_comments,
+    })
 }
 
-fn convert_root<'src>(arena: &'src Arena<'src>, root: &'src SyntaxElement<'src>) -> ArenaDoc<'src> {
+fn convert_root<'src>(
+    arena: &'src DocArena<'src>,
+    root: &'src SyntaxElement<'src>,
+    ctx: &Context,
+) -> ArenaDoc<'src> {
+    let (regions, _) = directive::regions(root.children());
     let mut iter = root.children().peekable();
     let mut doc = arena.nil();
+    let mut previous_is_definition = false;
 
     while iter.peek().is_some() {
         let ignore_leading_newlines = matches!(*doc, Doc::Nil);
 
-        let leading_trivia = iter.collect_while(|e| is_leading_trivia(e.kind()));
+        let leading_trivia =
+            iter.collect_while(|e| is_leading_trivia(e.kind()) && region_at(&regions, e).is_none());
         let expr = iter.next();
+        let region = expr
+            .and_then(|e| region_at(&regions, e))
+            .map(|region| convert_region(arena, &mut iter, region, ctx));
         let trailing_trivia = iter.collect_while(|e| is_trailing_trivia(e.kind()));
 
-        let ignored = leading_trivia
-            .iter()
-            .chain(trailing_trivia.iter())
-            .cloned()
-            .any(|t| is_ignore_comment(t));
+        let trivia = || leading_trivia.iter().chain(trailing_trivia.iter()).cloned();
+        let ignored = trivia().any(|t| directive::ignores_expr(t));
+
+        let is_definition = expr.and_then(head_symbol).is_some_and(forms::is_definition);
+        let min_blank_lines = if previous_is_definition || is_definition {
+            ctx.config.blank_lines_between_definitions
+        } else {
+            0
+        };
+        previous_is_definition = is_definition;
 
         doc = doc.append(convert_leading_trivia(
             arena,
             &leading_trivia,
-            !ignore_leading_newlines,
+            CommentPosition::TopLevel,
+            ctx,
+            (!ignore_leading_newlines).then_some(min_blank_lines..=ctx.config.max_blank_lines),
             expr.is_some(),
         ));
 
-        if let Some(expr) = expr {
+        if let Some((region, _)) = region {
+            doc = doc.append(region);
+        } else if let Some(expr) = expr {
             if ignored {
-                doc = doc.append(expr.content());
+                doc = doc.append(convert_ignored(arena, expr, ctx));
             } else {
-                doc = doc.append(expr.to_doc(arena));
+                let config = expr_config(trivia(), ctx.config);
+                let expr_ctx = config.as_ref().map(|config| ctx.with_config(config));
+                doc = doc.append(expr.to_doc(arena, expr_ctx.as_ref().unwrap_or(ctx)));
             }
         }
 
         for trivia in trailing_trivia {
             if *trivia.kind() == SyntaxKind::Comment {
-                doc = doc.append(arena.space()).append(trivia.text().trim_end());
+                doc = doc
+                    .append(arena.space())
+                    .append(trailing_comment(arena, trivia, ctx));
             }
         }
 
@@ -124,44 +615,58 @@ fn convert_root<'src>(arena: &'src Arena<'src>, root: &'src SyntaxElement<'src>)
 }
 
 fn convert_list_like<'src>(
-    arena: &'src Arena<'src>,
+    arena: &'src DocArena<'src>,
     elem: &'src SyntaxElement<'src>,
-    indent: isize,
+    ctx: &Context,
     keep_original_linebreaks: bool,
+    padding: &[usize],
 ) -> ArenaDoc<'src> {
     let [open, exprs @ .., close] = &elem.children().collect::<Vec<_>>()[..] else {
         panic!("Container is missing an opening or closing delimiter.");
     };
+    let indent = container_indent(elem.kind(), ctx.config).expect("Element should be a container.");
 
+    let (regions, _) = directive::regions(exprs.iter().cloned());
     let mut iter = exprs.iter().cloned().peekable();
-    let mut doc = open.to_doc(arena);
-    let mut has_leading_ignore_comment = false;
+    let bindings = head_symbol(elem).is_some_and(forms::is_binding_form);
+    let docstring = docstring_index(elem, ctx.config);
+
+    let mut doc = open.to_doc(arena, ctx);
+    // A comment on the line of the opening delimiter applies to the first expression
+    let mut opening_comment = None;
 
     // Skip trivia until the first comment
-    while let Some(trivia) = iter.next_if(|t| t.kind().is_trivia()) {
+    while let Some(trivia) =
+        iter.next_if(|t| t.kind().is_trivia() && region_at(&regions, t).is_none())
+    {
         if *trivia.kind() == SyntaxKind::Comment {
+            // The comment ends up on the same line as the opening delimiter
             doc = doc
-                .append(trivia.text().trim_end())
+                .append(comment(arena, trivia, CommentPosition::Trailing, ctx))
                 .append(arena.hardline());
-            has_leading_ignore_comment = is_ignore_comment(trivia);
+            opening_comment = Some(trivia);
             break;
         }
     }
 
-    let allow_leading_empty_line_after_first_newline = indent <= 1;
+    let allow_leading_empty_line_after_first_newline = *elem.kind() != SyntaxKind::List;
 
     // TODO: Avoid mutating state?
+    let mut index = 0;
     let mut first_expr = true;
     let mut first_newline_found = false;
     let mut last_expr_has_trailing_comment = false;
 
     let leading_trivia = loop {
-        let leading_trivia = iter.collect_while(|e| is_leading_trivia(e.kind()));
+        let leading_trivia =
+            iter.collect_while(|e| is_leading_trivia(e.kind()) && region_at(&regions, e).is_none());
 
         // If there is no expr, this trivia belongs to the closing delimiter
         let Some(expr) = iter.next() else {
             break leading_trivia;
         };
+        let region =
+            region_at(&regions, expr).map(|region| convert_region(arena, &mut iter, region, ctx));
 
         let trailing_trivia = iter.collect_while(|e| is_trailing_trivia(e.kind()));
 
@@ -191,24 +696,49 @@ fn convert_list_like<'src>(
         expr_doc = expr_doc.append(convert_leading_trivia(
             arena,
             &leading_trivia,
-            allow_leading_empty_newline,
+            CommentPosition::Inner,
+            ctx,
+            allow_leading_empty_newline.then_some(0..=ctx.config.max_inner_blank_lines),
             true,
         ));
 
         first_newline_found = first_newline_found || has_leading_newline;
 
-        let ignored = first_expr && has_leading_ignore_comment
-            || leading_trivia
-                .iter()
-                .chain(trailing_trivia.iter())
-                .cloned()
-                .any(|t| is_ignore_comment(t));
-
-        // TODO: Break group when ignored is multiline
-        if ignored {
-            expr_doc = expr_doc.append(expr.content());
+        let trivia = || {
+            opening_comment
+                .filter(|_| first_expr)
+                .into_iter()
+                .chain(leading_trivia.iter().chain(trailing_trivia.iter()).cloned())
+        };
+        let ignored = trivia().any(|t| directive::ignores_expr(t));
+
+        if let Some((region, exprs)) = region {
+            expr_doc = expr_doc.append(region);
+            // The region ends with a comment, and the expressions it contains are skipped
+            last_expr_has_trailing_comment = true;
+            index += exprs;
+            doc = doc.append(expr_doc);
+            first_expr = false;
+            continue;
+        } else if ignored {
+            expr_doc = expr_doc.append(convert_ignored(arena, expr, ctx));
         } else {
-            expr_doc = expr_doc.append(expr.to_doc(arena));
+            let config = expr_config(trivia(), ctx.config);
+            let expr_ctx = config.as_ref().map(|config| ctx.with_config(config));
+            let ctx = expr_ctx.as_ref().unwrap_or(ctx);
+
+            let padding = padding.get(index).copied().unwrap_or(0);
+            expr_doc = expr_doc.append(match expr.kind() {
+                SyntaxKind::Pair => convert_pair(arena, expr, ctx, padding),
+                // The binding vector follows the name of the binding form
+                SyntaxKind::Sequence if bindings && index == 1 => {
+                    convert_sequence(arena, expr, ctx, true)
+                }
+                _ if docstring == Some(index) => convert_docstring(arena, expr, ctx),
+                _ => expr
+                    .to_doc(arena, ctx)
+                    .append(align_padding(arena, padding)),
+            });
         }
 
         last_expr_has_trailing_comment = false;
@@ -217,7 +747,7 @@ fn convert_list_like<'src>(
                 // TODO: This should add a hardline
                 expr_doc = expr_doc
                     .append(arena.space())
-                    .append(trivia.text().trim_end())
+                    .append(trailing_comment(arena, trivia, ctx))
                     .append(arena.break_group());
 
                 last_expr_has_trailing_comment = true;
@@ -225,6 +755,7 @@ fn convert_list_like<'src>(
         }
 
         doc = doc.append(expr_doc);
+        index += 1;
         first_expr = false;
     };
 
@@ -239,11 +770,14 @@ fn convert_list_like<'src>(
     doc = doc.append(convert_leading_trivia(
         arena,
         &leading_trivia,
-        !exprs.iter().all(|e| e.kind().is_trivia()),
+        CommentPosition::Inner,
+        ctx,
+        (!exprs.iter().all(|e| e.kind().is_trivia()))
+            .then_some(0..=ctx.config.max_inner_blank_lines),
         false,
     ));
 
-    doc = doc.append(close.to_doc(arena)).hang(indent);
+    doc = doc.append(close.to_doc(arena, ctx)).hang(indent);
 
     // Make sure line breaks are kept even when inside a grouped container
     if keep_original_linebreaks {
@@ -254,12 +788,15 @@ fn convert_list_like<'src>(
 }
 
 fn convert_leading_trivia<'src>(
-    arena: &'src Arena<'src>,
+    arena: &'src DocArena<'src>,
     leading_trivia: &Vec<&'src SyntaxElement<'src>>,
-    allow_leading_newline: bool,
+    position: CommentPosition,
+    ctx: &Context,
+    blank_lines: Option<RangeInclusive<usize>>,
     allow_trailing_newline: bool,
 ) -> ArenaDoc<'src> {
-    let mut track_newlines = allow_leading_newline;
+    let mut track_newlines = blank_lines.is_some();
+    let mut blank_lines = blank_lines.unwrap_or(0..=0);
     let mut consecutive_newlines = 0;
     let mut doc = arena.nil();
 
@@ -269,13 +806,16 @@ fn convert_leading_trivia<'src>(
                 consecutive_newlines += 1;
             }
             SyntaxKind::Comment => {
-                if consecutive_newlines >= 2 {
-                    doc = doc.append(arena.hardline());
-                }
-
                 doc = doc
-                    .append(trivia.text().trim_end())
+                    .append(empty_lines(arena, consecutive_newlines, &blank_lines))
+                    .append(comment(arena, trivia, position, ctx).annotate(
+                        Annotation::LineComment {
+                            reflow: ctx.config.reflow_comments,
+                        },
+                    ))
                     .append(arena.hardline());
+                // Only the lines before the first comment are required to be blank
+                blank_lines = 0..=*blank_lines.end();
                 track_newlines = true;
                 consecutive_newlines = 0;
             }
@@ -283,9 +823,23 @@ fn convert_leading_trivia<'src>(
         }
     }
 
-    if allow_trailing_newline && consecutive_newlines >= 2 {
-        doc = doc.append(arena.hardline());
+    if allow_trailing_newline {
+        doc = doc.append(empty_lines(arena, consecutive_newlines, &blank_lines));
     }
 
     doc
 }
+
+/// Returns the empty lines kept from the given number of consecutive line breaks, clamped to
+/// `range`.
+fn empty_lines<'src>(
+    arena: &'src DocArena<'src>,
+    newlines: usize,
+    range: &RangeInclusive<usize>,
+) -> ArenaDoc<'src> {
+    let count = newlines
+        .saturating_sub(1)
+        .min(*range.end())
+        .max(*range.start());
+    arena.concat((0..count).map(|_| arena.hardline()))
+}
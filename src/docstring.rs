@@ -0,0 +1,130 @@
+use crate::{config::Config, render::is_reflowable};
+
+/// Reindents the continuation lines of a docstring that moved from `original_column` to `column`,
+/// keeping their indentation relative to the opening quote.
+///
+/// Docstrings whose continuation lines all start at the beginning of the line, as is conventional
+/// in Emacs Lisp, are not reindented. When wrapping is enabled, paragraphs are also refilled to
+/// the maximum width.
+pub fn reindent(text: &str, original_column: usize, column: usize, config: &Config) -> String {
+    let mut lines = text.split('\n').map(str::to_string).collect::<Vec<_>>();
+
+    let base = lines[1..]
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| indentation(line, config))
+        .min();
+
+    let new_base = match base {
+        Some(0) => 0,
+        Some(base) => (base + column).saturating_sub(original_column),
+        // Lines created by wrapping a single line docstring line up with the opening quote
+        None => column,
+    };
+
+    if let Some(base) = base.filter(|base| *base > 0) {
+        for line in lines[1..].iter_mut().filter(|line| !line.trim().is_empty()) {
+            let width = indentation(line, config);
+            let text = line.trim_start_matches([' ', '\t']);
+            *line = format!("{}{}", " ".repeat(new_base + width - base), text);
+        }
+    }
+
+    if config.wrap_docstrings {
+        lines = wrap(lines, column, new_base, config);
+    }
+
+    lines.join("\n")
+}
+
+/// Returns the width of the leading whitespace of a line.
+fn indentation(line: &str, config: &Config) -> usize {
+    let text = line.trim_start_matches([' ', '\t']);
+    config.visual_width(&line[..line.len() - text.len()], 0)
+}
+
+/// Returns the byte offset at which the leading whitespace of a line reaches `column`, or the end
+/// of the leading whitespace if it is narrower.
+fn indentation_offset(line: &str, column: usize, tab_width: usize) -> usize {
+    let mut width = 0;
+    for (i, c) in line.char_indices() {
+        if width >= column || !matches!(c, ' ' | '\t') {
+            return i;
+        }
+        width = match c {
+            '\t' => (width / tab_width + 1) * tab_width,
+            _ => width + 1,
+        };
+    }
+
+    line.len()
+}
+
+/// Refills the paragraphs of a docstring, keeping blank lines, list items and indented code
+/// examples as written.
+fn wrap(lines: Vec<String>, column: usize, base: usize, config: &Config) -> Vec<String> {
+    let mut result = vec![];
+    let mut words: Vec<String> = vec![];
+
+    for (i, line) in lines.into_iter().enumerate() {
+        let content = if i == 0 {
+            line.strip_prefix('"').unwrap_or(&line)
+        } else {
+            &line[indentation_offset(&line, base, config.tab_width)..]
+        };
+
+        if !is_reflowable(content) || content == "\"" {
+            fill(&mut result, &mut words, column, base, config);
+            result.push(line);
+            continue;
+        }
+
+        let start = words.len();
+        words.extend(content.split_whitespace().map(str::to_string));
+        if i == 0 {
+            words[start].insert(0, '"');
+        }
+    }
+
+    fill(&mut result, &mut words, column, base, config);
+    result
+}
+
+/// Fills lines with `words` greedily and appends them to `lines`.
+fn fill(
+    lines: &mut Vec<String>,
+    words: &mut Vec<String>,
+    column: usize,
+    base: usize,
+    config: &Config,
+) {
+    if words.is_empty() {
+        return;
+    }
+
+    // The first line of the docstring starts after the text preceding the opening quote
+    let mut start = if lines.is_empty() { column } else { base };
+    let mut line = if lines.is_empty() {
+        String::new()
+    } else {
+        " ".repeat(base)
+    };
+    let mut width = start;
+
+    for word in words.drain(..) {
+        let word_width = config.width(&word);
+        if width > start && width + 1 + word_width > config.max_width {
+            lines.push(std::mem::replace(&mut line, " ".repeat(base)));
+            start = base;
+            width = base;
+        }
+        if width > start {
+            line.push(' ');
+            width += 1;
+        }
+        line.push_str(&word);
+        width += word_width;
+    }
+
+    lines.push(line);
+}
@@ -0,0 +1,226 @@
+//! Support for the `.editorconfig` files of a project, see <https://editorconfig.org>.
+
+use std::{fs, num::NonZeroUsize, path::Path};
+
+use crate::config::{Config, EndOfLine, IndentStyle, Options};
+
+/// Returns the options set by the `.editorconfig` files that apply to `path`.
+///
+/// Files are read from the directory of `path` up to the first one marked with `root = true`, and
+/// sections closer to `path` take precedence. `indent_size = tab` sets the list indentation to the
+/// tab width. Properties without a lispfmt equivalent and invalid values are ignored.
+pub fn options(path: &Path) -> Options {
+    let Ok(path) = std::path::absolute(path) else {
+        return Options::default();
+    };
+
+    let mut files = vec![];
+    for dir in path.ancestors().skip(1) {
+        let Ok(text) = fs::read_to_string(dir.join(".editorconfig")) else {
+            continue;
+        };
+        let file = parse(&text);
+        let root = file.root;
+        files.push((dir, file));
+        if root {
+            break;
+        }
+    }
+
+    let mut options = Options::default();
+    // `indent_size = tab` stands for the tab width, which may be set by a later property
+    let mut indent_size_is_tab = false;
+    for (dir, file) in files.iter().rev() {
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+
+        for section in &file.sections {
+            if matches(&section.glob, &relative) {
+                for (key, value) in &section.properties {
+                    if key == "indent_size" {
+                        indent_size_is_tab = value == "tab";
+                    }
+                    apply(&mut options, key, value);
+                }
+            }
+        }
+    }
+
+    if indent_size_is_tab {
+        let tab_width = options.tab_width.map(NonZeroUsize::get);
+        options.list_indent = Some(tab_width.unwrap_or(Config::default().tab_width));
+    }
+
+    options
+}
+
+struct File {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+struct Section {
+    glob: String,
+    properties: Vec<(String, String)>,
+}
+
+fn parse(text: &str) -> File {
+    let mut file = File {
+        root: false,
+        sections: vec![],
+    };
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+
+        if let Some(glob) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            file.sections.push(Section {
+                glob: glob.to_string(),
+                properties: vec![],
+            });
+        } else if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_lowercase();
+            match file.sections.last_mut() {
+                Some(section) => section.properties.push((key, value)),
+                None if key == "root" => file.root = value == "true",
+                None => (),
+            }
+        }
+    }
+
+    file
+}
+
+/// Sets the option corresponding to an `.editorconfig` property.
+fn apply(options: &mut Options, key: &str, value: &str) {
+    match key {
+        "indent_style" => {
+            options.indent_style = match value {
+                "space" => Some(IndentStyle::Spaces),
+                "tab" => Some(IndentStyle::Tabs),
+                _ => None,
+            }
+        }
+        "indent_size" => options.list_indent = value.parse().ok(),
+        "tab_width" => options.tab_width = value.parse().ok(),
+        "max_line_length" => options.max_width = value.parse().ok(),
+        "end_of_line" => {
+            options.end_of_line = match value {
+                "lf" => Some(EndOfLine::Lf),
+                "crlf" => Some(EndOfLine::Crlf),
+                _ => None,
+            }
+        }
+        "insert_final_newline" => options.insert_final_newline = value.parse().ok(),
+        _ => (),
+    }
+}
+
+/// Returns whether a section glob matches a path relative to the directory of its file.
+///
+/// A glob without a `/` matches a file name in any directory. Numeric ranges such as `{1..3}` are
+/// not supported.
+fn matches(glob: &str, path: &str) -> bool {
+    let glob = match glob.strip_prefix('/') {
+        Some(glob) => glob.to_string(),
+        None if glob.contains('/') => glob.to_string(),
+        None => format!("**/{}", glob),
+    };
+
+    expand_braces(&glob).iter().any(|glob| {
+        let glob = glob.chars().collect::<Vec<_>>();
+        let path = path.chars().collect::<Vec<_>>();
+        // `**/` also matches no directories at all
+        match glob.strip_prefix(&['*', '*', '/']) {
+            Some(rest) => matches_chars(&glob, &path) || matches_chars(rest, &path),
+            None => matches_chars(&glob, &path),
+        }
+    })
+}
+
+/// Expands the alternatives of `{a,b}` into separate globs.
+fn expand_braces(glob: &str) -> Vec<String> {
+    let Some(open) = glob.find('{') else {
+        return vec![glob.to_string()];
+    };
+
+    let mut depth = 0;
+    let mut alternatives = vec![];
+    let mut start = open + 1;
+    for (i, c) in glob.char_indices().skip_while(|(i, _)| *i <= open) {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            ',' if depth == 0 => {
+                alternatives.push(&glob[start..i]);
+                start = i + 1;
+            }
+            '}' => {
+                alternatives.push(&glob[start..i]);
+                let (prefix, suffix) = (&glob[..open], &glob[i + 1..]);
+                return alternatives
+                    .into_iter()
+                    .flat_map(|alternative| {
+                        expand_braces(&format!("{}{}{}", prefix, alternative, suffix))
+                    })
+                    .collect();
+            }
+            _ => (),
+        }
+    }
+
+    // An unclosed brace is matched literally
+    vec![glob.to_string()]
+}
+
+fn matches_chars(glob: &[char], path: &[char]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| matches_chars(rest, &path[i..])),
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != '/')
+            .any(|i| matches_chars(rest, &path[i..])),
+        ['?', rest @ ..] => matches!(path, [c, ..] if *c != '/') && matches_chars(rest, &path[1..]),
+        ['[', class @ ..] if class.contains(&']') => {
+            let end = class
+                .iter()
+                .position(|&c| c == ']')
+                .expect("Class should be closed.");
+            let (negated, set) = match &class[..end] {
+                ['!', set @ ..] => (true, set),
+                set => (false, set),
+            };
+            matches!(path, [c, ..] if *c != '/' && in_set(set, *c) != negated)
+                && matches_chars(&class[end + 1..], &path[1..])
+        }
+        ['\\', c, rest @ ..] | [c, rest @ ..] => {
+            path.first() == Some(c) && matches_chars(rest, &path[1..])
+        }
+    }
+}
+
+/// Returns whether a character is in the set of a `[...]` class, such as `a-z_`.
+fn in_set(set: &[char], c: char) -> bool {
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            if (set[i]..=set[i + 2]).contains(&c) {
+                return true;
+            }
+            i += 3;
+        } else {
+            if set[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+
+    false
+}
@@ -1,40 +1,77 @@
-use ariadne::{Color, Label, Report, ReportKind, Source};
 use chumsky::error::Rich;
 
-use crate::node::Span;
+use crate::{diagnostic::Diagnostic, error_code::ErrorCode, kind::SyntaxKind, node::Span};
 
 // TODO: Combine errors
 #[derive(Debug)]
 pub enum Error<'src> {
     Lex(Vec<Rich<'src, char>>),
-    Parse(Vec<(String, Span)>),
+    Parse(Vec<ParseError>),
+    /// The formatted output of a valid source cannot be processed, which is a bug in the formatter.
+    Internal(&'static str),
+    /// A structural edit of the text in a span would make the source unparseable.
+    InvalidEdit(Span),
 }
 
 impl<'src> Error<'src> {
-    pub fn print(self, src: &str) {
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
         match self {
-            Error::Lex(errs) => errs.into_iter().for_each(|e| {
-                build_report(&e.to_string(), e.span())
-                    .eprint(Source::from(&src))
-                    .unwrap()
-            }),
-            Error::Parse(errs) => errs.into_iter().for_each(|(ref reason, span)| {
-                build_report(reason, &span)
-                    .eprint(Source::from(&src))
-                    .unwrap()
-            }),
+            Error::Lex(errs) => errs
+                .iter()
+                .map(|e| Diagnostic::error(ErrorCode::InvalidToken, e.to_string(), *e.span()))
+                .collect(),
+            Error::Parse(errs) => errs
+                .iter()
+                .map(|e| Diagnostic::error(e.code(), e.message(), e.span()))
+                .collect(),
+            Error::Internal(message) => vec![Diagnostic::error(
+                ErrorCode::Internal,
+                format!("internal error: {}", message),
+                (0..0).into(),
+            )],
+            Error::InvalidEdit(span) => vec![Diagnostic::error(
+                ErrorCode::InvalidEdit,
+                "the edit would make the code unparseable",
+                *span,
+            )],
         }
     }
 }
 
-fn build_report<'a>(reason: &'a str, span: &'a Span) -> Report<'a> {
-    Report::build(ReportKind::Error, span.into_range())
-        .with_config(ariadne::Config::new().with_index_type(ariadne::IndexType::Byte))
-        .with_message(reason)
-        .with_label(
-            Label::new(span.into_range())
-                .with_message(reason.to_string())
-                .with_color(Color::Red),
-        )
-        .finish()
+#[derive(Debug)]
+pub enum ParseError {
+    /// A container reached the end of input without its closing delimiter.
+    UnclosedDelimiter { open: SyntaxKind, span: Span },
+    /// A closing delimiter that does not close the innermost container.
+    UnexpectedClosingDelimiter { found: SyntaxKind, span: Span },
+    /// A table key that is not followed by a value.
+    MissingTableValue { span: Span },
+}
+
+impl ParseError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ParseError::UnclosedDelimiter { .. } => ErrorCode::UnclosedDelimiter,
+            ParseError::UnexpectedClosingDelimiter { .. } => ErrorCode::UnexpectedClosingDelimiter,
+            ParseError::MissingTableValue { .. } => ErrorCode::MissingTableValue,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ParseError::UnclosedDelimiter { open, .. } => format!("unclosed {}", open.name()),
+            ParseError::UnexpectedClosingDelimiter { found, .. } => {
+                format!("unexpected {}", found.name())
+            }
+            ParseError::MissingTableValue { .. } => "table key has no value".to_string(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnclosedDelimiter { span, .. }
+            | ParseError::UnexpectedClosingDelimiter { span, .. }
+            | ParseError::MissingTableValue { span } => *span,
+        }
+    }
 }
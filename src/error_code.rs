@@ -0,0 +1,341 @@
+use std::{fmt, str::FromStr};
+
+/// A stable identifier for every kind of diagnostic lispfmt can report.
+///
+/// Codes are never reused or renumbered, so they can be searched for and suppressed by other tools.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ErrorCode {
+    /// `LF001`: A container is never closed.
+    UnclosedDelimiter,
+    /// `LF002`: A closing delimiter does not close anything.
+    UnexpectedClosingDelimiter,
+    /// `LF003`: A table has a key without a value.
+    MissingTableValue,
+    /// `LF004`: The input contains text that is not a valid token.
+    InvalidToken,
+    /// `LF005`: The input is not valid UTF-8.
+    InvalidUtf8,
+    /// `LF006`: A `lispfmt.toml` file is invalid.
+    InvalidConfig,
+    /// `LF007`: A structural edit would make the code unparseable.
+    InvalidEdit,
+    /// `LF008`: A file cannot be read or written.
+    Io,
+    /// `LF009`: lispfmt failed in a way that is a bug in lispfmt.
+    Internal,
+    /// `LF100`: The file is not formatted.
+    Unformatted,
+    /// `LF101`: A `lispfmt-off` or `lispfmt-on` marker has no counterpart.
+    UnmatchedMarker,
+    /// `LF102`: A `lispfmt-ignore` comment names a rule that does not exist.
+    UnknownRule,
+    /// `LF103`: Formatting the formatted output changes it again.
+    NotIdempotent,
+    /// `LF104`: The formatted output does not contain the same code as the input.
+    ChangedTokens,
+    /// `LF105`: A `lispfmt-ignore` comment does not apply to any form.
+    UnusedDirective,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 15] = [
+        ErrorCode::UnclosedDelimiter,
+        ErrorCode::UnexpectedClosingDelimiter,
+        ErrorCode::MissingTableValue,
+        ErrorCode::InvalidToken,
+        ErrorCode::InvalidUtf8,
+        ErrorCode::InvalidConfig,
+        ErrorCode::InvalidEdit,
+        ErrorCode::Io,
+        ErrorCode::Internal,
+        ErrorCode::Unformatted,
+        ErrorCode::UnmatchedMarker,
+        ErrorCode::UnknownRule,
+        ErrorCode::NotIdempotent,
+        ErrorCode::ChangedTokens,
+        ErrorCode::UnusedDirective,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnclosedDelimiter => "LF001",
+            ErrorCode::UnexpectedClosingDelimiter => "LF002",
+            ErrorCode::MissingTableValue => "LF003",
+            ErrorCode::InvalidToken => "LF004",
+            ErrorCode::InvalidUtf8 => "LF005",
+            ErrorCode::InvalidConfig => "LF006",
+            ErrorCode::InvalidEdit => "LF007",
+            ErrorCode::Io => "LF008",
+            ErrorCode::Internal => "LF009",
+            ErrorCode::Unformatted => "LF100",
+            ErrorCode::UnmatchedMarker => "LF101",
+            ErrorCode::UnknownRule => "LF102",
+            ErrorCode::NotIdempotent => "LF103",
+            ErrorCode::ChangedTokens => "LF104",
+            ErrorCode::UnusedDirective => "LF105",
+        }
+    }
+
+    /// A short summary of the diagnostic.
+    pub fn title(&self) -> &'static str {
+        match self {
+            ErrorCode::UnclosedDelimiter => "unclosed delimiter",
+            ErrorCode::UnexpectedClosingDelimiter => "unexpected closing delimiter",
+            ErrorCode::MissingTableValue => "missing table value",
+            ErrorCode::InvalidToken => "invalid token",
+            ErrorCode::InvalidUtf8 => "invalid UTF-8",
+            ErrorCode::InvalidConfig => "invalid configuration file",
+            ErrorCode::InvalidEdit => "invalid structural edit",
+            ErrorCode::Io => "I/O error",
+            ErrorCode::Internal => "internal error",
+            ErrorCode::Unformatted => "file would be reformatted",
+            ErrorCode::UnmatchedMarker => "unmatched formatting marker",
+            ErrorCode::UnknownRule => "unknown rule",
+            ErrorCode::NotIdempotent => "formatting is not idempotent",
+            ErrorCode::ChangedTokens => "formatting changed the code",
+            ErrorCode::UnusedDirective => "unused directive",
+        }
+    }
+
+    /// A longer description of the diagnostic, printed by `lispfmt explain`.
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            ErrorCode::UnclosedDelimiter => LF001,
+            ErrorCode::UnexpectedClosingDelimiter => LF002,
+            ErrorCode::MissingTableValue => LF003,
+            ErrorCode::InvalidToken => LF004,
+            ErrorCode::InvalidUtf8 => LF005,
+            ErrorCode::InvalidConfig => LF006,
+            ErrorCode::InvalidEdit => LF007,
+            ErrorCode::Io => LF008,
+            ErrorCode::Internal => LF009,
+            ErrorCode::Unformatted => LF100,
+            ErrorCode::UnmatchedMarker => LF101,
+            ErrorCode::UnknownRule => LF102,
+            ErrorCode::NotIdempotent => LF103,
+            ErrorCode::ChangedTokens => LF104,
+            ErrorCode::UnusedDirective => LF105,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = String;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.as_str().eq_ignore_ascii_case(code))
+            .ok_or_else(|| format!("unknown error code `{}`", code))
+    }
+}
+
+const LF001: &str = r#"An opening delimiter was never closed before the end of the input.
+
+Every `(`, `[` and `{` must be matched by a `)`, `]` or `}` respectively.
+
+Erroneous example:
+
+    (fn add [a b]
+      (+ a b)
+
+Add the missing closing delimiter:
+
+    (fn add [a b]
+      (+ a b))
+"#;
+
+const LF002: &str = r#"A closing delimiter was found that does not match the innermost open container.
+
+This usually means a delimiter is missing, is of the wrong kind, or there is one too many.
+
+Erroneous examples:
+
+    (print "hello"))
+    (let [x 1) x)
+
+Remove the extra delimiter or use the matching one:
+
+    (print "hello")
+    (let [x 1] x)
+"#;
+
+const LF003: &str = r#"A table contains a key without a corresponding value.
+
+Tables are made of key-value pairs, so they must contain an even number of expressions.
+
+Erroneous example:
+
+    {:name "lispfmt" :version}
+
+Add the missing value or remove the key:
+
+    {:name "lispfmt" :version "0.1.0"}
+"#;
+
+const LF004: &str = r#"The input contains text that cannot be read as a token.
+
+This is most often an unterminated string literal.
+
+Erroneous example:
+
+    (print "hello)
+
+Close the string literal:
+
+    (print "hello")
+"#;
+
+const LF005: &str = r#"The input is not valid UTF-8.
+
+lispfmt only reads UTF-8 text. The diagnostic points at the first byte that is not part of a valid
+UTF-8 sequence, which is most often text saved in a legacy encoding such as Latin-1.
+
+Convert the file to UTF-8, or run lispfmt with `--lossy` to format it anyway and leave the invalid
+sequences unchanged.
+"#;
+
+const LF006: &str = r#"A `lispfmt.toml` file is not valid TOML or contains an unknown or invalid option.
+
+Options are named like the command-line flags, with underscores instead of dashes. Files that the
+configuration file applies to are not formatted until it is fixed.
+
+Erroneous example:
+
+    indent-style = "tab"
+
+Use the name and a value of the option:
+
+    indent_style = "tabs"
+
+It is also reported when the options set by configuration files and the command line together
+enable an option without one it requires, such as `wrap_docstrings` without `docstrings`, or
+set an option to a value outside of its range, such as `ambiguous_width = 3`.
+"#;
+
+const LF007: &str = r#"A structural edit would make the code unparseable.
+
+Reported by `lispfmt::paredit` when an operation would separate a table key from its value, so the
+edit is not applied.
+
+Erroneous example, barfing in a table:
+
+    {:a 1 :b |2}
+
+Barfing `2` out of the table would leave `:b` without a value. Barf the whole pair instead by
+moving it into its own container first, or edit the table by hand.
+"#;
+
+const LF008: &str = r#"A file cannot be read or written.
+
+Reported when an input file, standard input or a `lispfmt.toml` file cannot be read, or when the
+formatted output cannot be written back to a file or to standard output. The message includes the
+error reported by the operating system, such as a missing file or a denied permission. The other
+files are still formatted.
+"#;
+
+const LF009: &str = r#"lispfmt failed in a way that is a bug in lispfmt.
+
+Reported when formatting part of a file, such as a range formatted by an editor or the code around
+a structural edit, and the formatted output of the whole file cannot be parsed or does not have
+the same top-level expressions as the input, so the formatted part cannot be located in it. The
+input is left unchanged. Please report it along with the input that caused it.
+"#;
+
+const LF100: &str = r#"The file is not formatted.
+
+Reported by `lispfmt --check` for each file whose contents differ from the formatted output.
+The diagnostic points at the first difference.
+
+Run lispfmt without `--check` to print the formatted file.
+"#;
+
+const LF101: &str = r#"A `lispfmt-off` comment has no `lispfmt-on` comment after it, or the other way around.
+
+Code between `; lispfmt-off` and `; lispfmt-on` is kept exactly as written. Both comments must be
+in the same container, so a region cannot start in one form and end in another. An unmatched
+marker is ignored and the code around it is formatted as usual.
+
+Erroneous example:
+
+    (def table
+      ; lispfmt-off
+      {:a 1   :b 2
+       :c 3   :d 4})
+
+Add the missing marker:
+
+    (def table
+      ; lispfmt-off
+      {:a 1   :b 2
+       :c 3   :d 4}
+      ; lispfmt-on
+      )
+"#;
+
+const LF102: &str = r#"A `lispfmt-ignore` comment names a rule that does not exist.
+
+`; lispfmt-ignore <rule>` disables an optional rule for the expression after it. The rules that
+can be disabled this way are `align`, `align_comments`, `normalize_comments`, `reflow_comments`
+and `docstrings`. Unknown rules are ignored. A reason for the comment can follow `--`:
+
+    ; lispfmt-ignore align -- matches the table in the spec
+
+Erroneous example:
+
+    ; lispfmt-ignore alignment
+    {:a 1
+     :bbb 2}
+
+Use the name of an existing rule:
+
+    ; lispfmt-ignore align
+    {:a 1
+     :bbb 2}
+"#;
+
+const LF103: &str = r#"Formatting the formatted output of a file changes it again.
+
+Reported by `lispfmt --verify`. Formatting should be idempotent, so this is a bug in lispfmt.
+A diff between the formatted output and the output of formatting it again is printed after the
+diagnostic, and included in the JSON and SARIF output. Please report it along with the input that
+caused it.
+"#;
+
+const LF104: &str = r#"The formatted output does not contain the same code as the input.
+
+Reported by `lispfmt --verify`, and by `lispfmt --write` before a file is overwritten, in which
+case the file is left unchanged. The check before writing can be skipped with
+`--no-equivalence-check`. Formatting should only change whitespace, the semicolons of comments
+and the whitespace inside docstrings, so this is a bug in lispfmt. The diagnostic points
+at the first token of the input that is missing or changed in the output, and at the
+corresponding token of the output. Please report it along with the input that caused it.
+"#;
+
+const LF105: &str = r#"A `lispfmt-ignore` comment does not apply to any form.
+
+A `lispfmt-ignore` comment applies to the expression it follows on the same line, or otherwise to
+the expression after it. It has no effect when that expression is a single token, such as a
+symbol, or when there is no expression after it in its container.
+
+Erroneous example, where the comment follows `do` and not the `let` form:
+
+    (do ; lispfmt-ignore align
+      (let [a 1
+            bbb 2]
+        a))
+
+Put the comment on its own line before the form:
+
+    (do
+      ;; lispfmt-ignore align
+      (let [a 1
+            bbb 2]
+        a))
+"#;
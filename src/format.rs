@@ -1,17 +1,97 @@
 use pretty::Arena;
 
-use crate::{error::Error, parser::parse};
+use crate::{
+    config::{Bom, Config},
+    directive::ignores_file,
+    doc::Context,
+    error::Error,
+    line_index::LineIndex,
+    node::{Span, SyntaxElement},
+    parser::parse,
+    render::render,
+};
 
-pub fn format_text<'src>(src: &'src str) -> Result<String, Error<'src>> {
+/// A replacement of the text in `span` with `text`.
+#[derive(Debug, PartialEq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub text: String,
+}
+
+pub fn format_text<'src>(src: &'src str, config: &Config) -> Result<String, Error<'src>> {
+    let tree = parse(src)?;
+    if ignores_file(&tree) {
+        return Ok(src.to_string());
+    }
+
+    let arena = Arena::new();
+    let line_index = LineIndex::new(src);
+    let ctx = Context {
+        config,
+        line_index: &line_index,
+    };
+
+    let line_ending = config.end_of_line.resolve(src);
+    let mut formatted = render(tree.to_doc(&arena, &ctx), config, line_ending);
+    if config.bom == Bom::Keep && src.starts_with('\u{feff}') {
+        formatted.insert(0, '\u{feff}');
+    }
+    if config.insert_final_newline && !formatted.is_empty() && !formatted.ends_with(['\n', '\r']) {
+        formatted.push_str(line_ending);
+    }
+
+    Ok(formatted)
+}
+
+/// Formats the top-level expressions that overlap `range`.
+///
+/// Returns `None` if those expressions are already formatted, and an [`Error::Internal`] if the
+/// formatted output does not have the same top-level expressions as `src`.
+pub fn format_range<'src>(
+    src: &'src str,
+    range: Span,
+    config: &Config,
+) -> Result<Option<TextEdit>, Error<'src>> {
     let tree = parse(src)?;
-    let arena = Arena::<()>::new();
+    let formatted = format_text(src, config)?;
+    let formatted_tree =
+        parse(&formatted).map_err(|_| Error::Internal("the formatted output cannot be parsed"))?;
+
+    let original = top_level_spans(&tree);
+    let formatted_spans = top_level_spans(&formatted_tree);
+    if original.len() != formatted_spans.len() {
+        return Err(Error::Internal(
+            "formatting changed the number of top-level expressions",
+        ));
+    }
+
+    let mut overlapping = original
+        .iter()
+        .enumerate()
+        .filter(|(_, span)| span.start <= range.end && range.start <= span.end)
+        .map(|(i, _)| i);
+
+    let Some(first) = overlapping.next() else {
+        return Ok(None);
+    };
+    let last = overlapping.next_back().unwrap_or(first);
 
-    let formatted = tree.to_doc(&arena).pretty(100).to_string();
+    let span = Span::from(original[first].start..original[last].end);
+    let text = &formatted[formatted_spans[first].start..formatted_spans[last].end];
+
+    if &src[span.into_range()] == text {
+        return Ok(None);
+    }
+
+    Ok(Some(TextEdit {
+        span,
+        text: text.to_string(),
+    }))
+}
 
-    // TODO: This might also remove whitespace from multiline strings
-    Ok(formatted
-        .split('\n')
-        .map(|s| s.trim_end())
-        .collect::<Vec<_>>()
-        .join("\n"))
+fn top_level_spans(root: &SyntaxElement) -> Vec<Span> {
+    root.children()
+        .filter(|e| !e.kind().is_trivia())
+        .map(|e| e.span())
+        .collect()
 }
@@ -0,0 +1,108 @@
+//! Knowledge about special forms shared by the dialects of the Lisp family.
+
+use crate::kind::SyntaxKind::{self, List, Sequence, Symbol};
+
+/// Forms whose first argument is a vector of name-value bindings, e.g. `(let [a 1 b 2] ...)`.
+const BINDING_FORMS: &[&str] = &[
+    "binding",
+    "doseq",
+    "for",
+    "if-let",
+    "if-some",
+    "let",
+    "loop",
+    "when-first",
+    "when-let",
+    "when-some",
+    "with-open",
+    "with-redefs",
+];
+
+/// Forms that define something at the top level, in Clojure, Common Lisp, Emacs Lisp and Scheme.
+const DEFINITION_FORMS: &[&str] = &[
+    "cl-defgeneric",
+    "cl-defmacro",
+    "cl-defmethod",
+    "cl-defstruct",
+    "cl-defun",
+    "def",
+    "defalias",
+    "defclass",
+    "defconst",
+    "defconstant",
+    "defcustom",
+    "defface",
+    "defgeneric",
+    "defgroup",
+    "define",
+    "define-derived-mode",
+    "define-minor-mode",
+    "define-record-type",
+    "define-syntax",
+    "define-values",
+    "definterface",
+    "defmacro",
+    "defmethod",
+    "defmulti",
+    "defn",
+    "defn-",
+    "defonce",
+    "defpackage",
+    "defparameter",
+    "defprotocol",
+    "defrecord",
+    "defstruct",
+    "defsubst",
+    "deftype",
+    "defun",
+    "defvar",
+];
+
+/// Forms made of test-expression clauses, with the number of expressions preceding the clauses,
+/// including the name of the form itself.
+const CLAUSE_FORMS: &[(&str, usize)] = &[("case", 2), ("cond", 1), ("match", 2)];
+
+/// Forms that can have a docstring, with the kinds of the expressions between the name of the form
+/// and its docstring in each dialect that has the form, e.g. `(defn name "doc" [args] ...)` in
+/// Clojure, `(defun name (args) "doc" ...)` in Emacs Lisp, `(define (name args) "doc" ...)` in
+/// Scheme or `(fn name [args] "doc" ...)` in Fennel.
+const DOCSTRING_FORMS: &[(&str, &[&[SyntaxKind]])] = &[
+    ("def", &[&[Symbol]]),
+    ("define", &[&[List]]),
+    ("defmacro", &[&[Symbol], &[Symbol, List]]),
+    ("defmulti", &[&[Symbol]]),
+    ("defn", &[&[Symbol]]),
+    ("defn-", &[&[Symbol]]),
+    ("defsubst", &[&[Symbol, List]]),
+    ("defun", &[&[Symbol, List]]),
+    ("fn", &[&[Sequence], &[Symbol, Sequence]]),
+    ("lambda", &[&[List], &[Sequence], &[Symbol, Sequence]]),
+    ("macro", &[&[Symbol, Sequence]]),
+    ("λ", &[&[Sequence], &[Symbol, Sequence]]),
+];
+
+/// Returns whether a form defines something at the top level, e.g. `def`, `defn` or `defun`.
+pub fn is_definition(name: &str) -> bool {
+    DEFINITION_FORMS.contains(&name)
+}
+
+pub fn is_binding_form(name: &str) -> bool {
+    BINDING_FORMS.contains(&name)
+}
+
+/// Returns the number of expressions preceding the clauses of a clause form.
+pub fn clause_offset(name: &str) -> Option<usize> {
+    CLAUSE_FORMS
+        .iter()
+        .find(|(form, _)| *form == name)
+        .map(|(_, offset)| *offset)
+}
+
+/// Returns the kinds of the expressions that can precede the docstring of a form after its name.
+pub fn docstring_positions(name: &str) -> &'static [&'static [SyntaxKind]] {
+    DOCSTRING_FORMS
+        .iter()
+        .find(|(form, _)| *form == name)
+        .map(|(_, positions)| *positions)
+        .unwrap_or_default()
+}
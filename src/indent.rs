@@ -0,0 +1,52 @@
+use crate::{
+    config::Config, doc::container_indent, kind::SyntaxKind, line_index::LineIndex,
+    node::SyntaxElement, parser::parse_partial,
+};
+
+/// Computes the column a new line inserted at `offset` should start at, counting tabs before it
+/// as reaching the next tab stop.
+///
+/// Only the text before `offset` is parsed, and forms that are still open at that point are
+/// indented the same way `doc.rs` indents a container that is broken onto multiple lines.
+///
+/// Returns `None` if the text before `offset` cannot be lexed, e.g. when the offset is inside a
+/// string.
+pub fn indent_for_newline(src: &str, offset: usize, config: &Config) -> Option<usize> {
+    let src = src.get(..offset.min(src.len()))?;
+    let tree = parse_partial(src).ok()?;
+    let line_index = LineIndex::new(src);
+
+    let mut indent = 0;
+    let mut elem = &tree;
+
+    // Forms that are still open can only be the last expression of their parent
+    while let Some(child) = elem
+        .children()
+        .rev()
+        .find(|e| !e.kind().is_trivia())
+        .filter(|e| is_open(e))
+    {
+        if let Some(container_indent) = container_indent(child.kind(), config) {
+            let column = config.visual_width(line_index.line_prefix(child.span().start), 0);
+            indent = column + container_indent as usize;
+        }
+        elem = child;
+    }
+
+    Some(indent)
+}
+
+/// Returns whether the element is missing a closing delimiter.
+fn is_open(elem: &SyntaxElement) -> bool {
+    let closing = match elem.kind() {
+        SyntaxKind::List => SyntaxKind::RParen,
+        SyntaxKind::Sequence => SyntaxKind::RBracket,
+        SyntaxKind::Table => SyntaxKind::RBrace,
+        SyntaxKind::Pair | SyntaxKind::Prefixed => {
+            return elem.children().last().is_some_and(is_open);
+        }
+        _ => return false,
+    };
+
+    elem.children().last().map(|e| *e.kind()) != Some(closing)
+}
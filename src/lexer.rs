@@ -118,13 +118,14 @@ fn lexer<'src>()
         .to(SyntaxKind::Space)
         .labelled("whitespace");
 
-    let newline = any()
-        .filter(|c: &char| is_newline(*c))
+    let newline = just("\r\n")
+        .or(just("\n"))
+        .or(just("\r"))
         .to(SyntaxKind::Newline)
         .labelled("newline");
 
     let comment = just(";")
-        .then(none_of("\n").repeated())
+        .then(none_of("\r\n").repeated())
         .to(SyntaxKind::Comment)
         .labelled("comment");
 
@@ -148,7 +149,7 @@ fn lexer<'src>()
     });
 
     let hash_directive = just("#")
-        .then(none_of("\n").repeated())
+        .then(none_of("\r\n").repeated())
         .to(SyntaxKind::HashDirective)
         .labelled("hash directive");
 
@@ -161,7 +162,14 @@ fn lexer<'src>()
         .or(symbol)
         .or(number);
 
+    // A byte-order mark is treated as whitespace
+    let bom = just('\u{feff}')
+        .to(SyntaxKind::Space)
+        .map_with(|kind, e| Token::new(kind, e.slice(), e.span()))
+        .or_not();
+
     group((
+        bom,
         trivia
             .or(hash_directive)
             .map_with(|kind, e| Token::new(kind, e.slice(), e.span()))
@@ -173,9 +181,9 @@ fn lexer<'src>()
             .repeated()
             .collect::<Vec<_>>(),
     ))
-    .map(|(mut directives, mut tokens)| {
+    .map(|(bom, mut directives, mut tokens)| {
         directives.append(&mut tokens);
-        directives
+        bom.into_iter().chain(directives).collect()
     })
 }
 
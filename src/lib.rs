@@ -0,0 +1,24 @@
+pub mod config;
+pub mod diagnostic;
+pub mod directive;
+mod doc;
+mod doc_ext;
+mod docstring;
+pub mod editorconfig;
+pub mod error;
+pub mod error_code;
+pub mod format;
+mod forms;
+pub mod indent;
+pub mod kind;
+mod lexer;
+pub mod line_index;
+pub mod lsp;
+pub mod node;
+pub mod paredit;
+pub mod parinfer;
+pub mod parser;
+mod peekable_ext;
+mod render;
+pub mod utf8;
+pub mod verify;
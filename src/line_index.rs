@@ -0,0 +1,123 @@
+/// The unit columns are counted in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Encoding {
+    /// UTF-8 bytes.
+    Utf8,
+    /// UTF-16 code units, as used by the Language Server Protocol.
+    Utf16,
+    /// Unicode scalar values.
+    Char,
+}
+
+impl Encoding {
+    fn len(&self, c: char) -> usize {
+        match self {
+            Encoding::Utf8 => c.len_utf8(),
+            Encoding::Utf16 => c.len_utf16(),
+            Encoding::Char => 1,
+        }
+    }
+}
+
+/// A 0-based line and column.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Converts between byte offsets and line/column positions in a source text.
+///
+/// `\n`, `\r\n` and a lone `\r` are all treated as line endings.
+pub struct LineIndex<'src> {
+    src: &'src str,
+    /// The byte offset of the start of every line.
+    line_starts: Vec<usize>,
+}
+
+impl<'src> LineIndex<'src> {
+    pub fn new(src: &'src str) -> Self {
+        let bytes = src.as_bytes();
+        let mut line_starts = vec![0];
+
+        for (i, b) in bytes.iter().enumerate() {
+            let is_line_end = match b {
+                b'\n' => true,
+                b'\r' => bytes.get(i + 1) != Some(&b'\n'),
+                _ => false,
+            };
+
+            if is_line_end {
+                line_starts.push(i + 1);
+            }
+        }
+
+        Self { src, line_starts }
+    }
+
+    pub fn text(&self) -> &'src str {
+        self.src
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the byte range of a line, excluding its line ending, or `None` if the line is past
+    /// the end of the source.
+    pub fn line_range(&self, line: usize) -> Option<std::ops::Range<usize>> {
+        let start = *self.line_starts.get(line)?;
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .copied()
+            .unwrap_or(self.src.len());
+        let text = &self.src[start..end];
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+
+        Some(start..start + text.len())
+    }
+
+    /// Converts a byte offset into a line and column.
+    ///
+    /// Offsets past the end of the source are clamped to the end, and offsets inside a character
+    /// to the start of that character.
+    pub fn line_col(&self, offset: usize, encoding: Encoding) -> LineCol {
+        let offset = self.src.floor_char_boundary(offset);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.src[self.line_starts[line]..offset]
+            .chars()
+            .map(|c| encoding.len(c))
+            .sum();
+
+        LineCol { line, column }
+    }
+
+    /// Returns the text of the line containing a byte offset, up to that offset.
+    pub fn line_prefix(&self, offset: usize) -> &'src str {
+        let offset = self.src.floor_char_boundary(offset);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        &self.src[self.line_starts[line]..offset]
+    }
+
+    /// Converts a line and column into a byte offset.
+    ///
+    /// Positions past the end of a line are clamped to the end of that line, and lines past the
+    /// end of the source are clamped to the end of the source.
+    pub fn offset(&self, pos: LineCol, encoding: Encoding) -> usize {
+        let Some(range) = self.line_range(pos.line) else {
+            return self.src.len();
+        };
+        let mut column = 0;
+
+        for (i, c) in self.src[range.clone()].char_indices() {
+            if column >= pos.column {
+                return range.start + i;
+            }
+            column += encoding.len(c);
+        }
+
+        range.end
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    path::{Path, PathBuf},
+};
+
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentFormattingParams, DocumentOnTypeFormattingOptions,
+    DocumentOnTypeFormattingParams, DocumentRangeFormattingParams, NumberOrString, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextEdit, Uri,
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as NotificationTrait, PublishDiagnostics,
+    },
+    request::{Formatting, OnTypeFormatting, RangeFormatting, Request as RequestTrait},
+};
+
+use crate::{
+    config::Config,
+    diagnostic::Severity,
+    directive,
+    format::{format_range, format_text},
+    indent::indent_for_newline,
+    line_index::{Encoding, LineCol, LineIndex},
+    node::Span,
+    parser::parse,
+};
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Runs a language server over stdin and stdout until the client shuts it down.
+///
+/// See [`serve`] for how the configuration of each document is resolved.
+pub fn run(resolve: impl FnMut(Option<&Path>) -> Option<Config>) -> Result<(), BoxError> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(&connection, resolve)?;
+
+    // The writer thread only stops once every sender is dropped
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+/// Runs a language server on `connection` until the client shuts it down.
+///
+/// The configuration of a document is resolved with `resolve` from its path when it is opened, or
+/// from `None` if it is not a file. Documents without a valid configuration are not formatted.
+pub fn serve(
+    connection: &Connection,
+    resolve: impl FnMut(Option<&Path>) -> Option<Config>,
+) -> Result<(), BoxError> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        document_range_formatting_provider: Some(OneOf::Left(true)),
+        document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+            first_trigger_character: "\n".to_string(),
+            more_trigger_character: None,
+        }),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server {
+        resolve,
+        documents: HashMap::new(),
+    };
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let response = server.handle_request(request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(notification) = server.handle_notification(notification) {
+                    connection
+                        .sender
+                        .send(Message::Notification(notification))?;
+                }
+            }
+            Message::Response(_) => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// An open document and the configuration it is formatted with.
+struct Document {
+    text: String,
+    config: Option<Config>,
+}
+
+struct Server<F> {
+    resolve: F,
+    documents: HashMap<Uri, Document>,
+}
+
+impl<F: FnMut(Option<&Path>) -> Option<Config>> Server<F> {
+    fn handle_request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+
+        let result = match request.method.as_str() {
+            Formatting::METHOD => extract::<Formatting>(request)
+                .map(|params| self.formatting(params))
+                .and_then(to_value),
+            RangeFormatting::METHOD => extract::<RangeFormatting>(request)
+                .map(|params| self.range_formatting(params))
+                .and_then(to_value),
+            OnTypeFormatting::METHOD => extract::<OnTypeFormatting>(request)
+                .map(|params| self.on_type_formatting(params))
+                .and_then(to_value),
+            method => {
+                return Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("unsupported request `{}`", method),
+                );
+            }
+        };
+
+        match result {
+            Ok(value) => Response::new_ok(id, value),
+            Err(message) => {
+                Response::new_err(id, lsp_server::ErrorCode::InvalidParams as i32, message)
+            }
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Option<Notification> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let uri = params.text_document.uri;
+                let config = (self.resolve)(file_path(&uri).as_deref());
+                let document = Document {
+                    text: params.text_document.text,
+                    config,
+                };
+                self.documents.insert(uri.clone(), document);
+                Some(self.publish_diagnostics(uri))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let uri = params.text_document.uri;
+                // Only full document synchronization is advertised, so the last change contains
+                // the whole document
+                let text = params.content_changes.into_iter().last()?.text;
+                self.documents.get_mut(&uri)?.text = text;
+                Some(self.publish_diagnostics(uri))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                Some(Notification::new(
+                    PublishDiagnostics::METHOD.to_string(),
+                    PublishDiagnosticsParams::new(uri, vec![], None),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn publish_diagnostics(&self, uri: Uri) -> Notification {
+        let src = &self.documents[&uri].text;
+        let line_index = LineIndex::new(src);
+
+        let diagnostics = match parse(src) {
+            Ok(tree) => directive::warnings(&tree),
+            Err(error) => error.diagnostics(),
+        };
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|d| Diagnostic {
+                range: to_range(&line_index, d.span),
+                severity: Some(match d.severity {
+                    Severity::Error => DiagnosticSeverity::ERROR,
+                    Severity::Warning => DiagnosticSeverity::WARNING,
+                }),
+                code: Some(NumberOrString::String(d.code.to_string())),
+                source: Some(env!("CARGO_PKG_NAME").to_string()),
+                message: d.message,
+                ..Default::default()
+            })
+            .collect();
+
+        Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            PublishDiagnosticsParams::new(uri, diagnostics, None),
+        )
+    }
+
+    fn formatting(&self, params: DocumentFormattingParams) -> Option<Vec<TextEdit>> {
+        let Document { text: src, config } = self.documents.get(&params.text_document.uri)?;
+        let formatted = format_text(src, config.as_ref()?).ok()?;
+
+        if *src == formatted {
+            return Some(vec![]);
+        }
+
+        let line_index = LineIndex::new(src);
+        Some(vec![TextEdit {
+            range: to_range(&line_index, (0..src.len()).into()),
+            new_text: formatted,
+        }])
+    }
+
+    fn range_formatting(&self, params: DocumentRangeFormattingParams) -> Option<Vec<TextEdit>> {
+        let Document { text: src, config } = self.documents.get(&params.text_document.uri)?;
+        let line_index = LineIndex::new(src);
+
+        let range = from_range(&line_index, params.range);
+        let edit = format_range(src, range, config.as_ref()?).ok()?;
+
+        Some(
+            edit.into_iter()
+                .map(|edit| TextEdit {
+                    range: to_range(&line_index, edit.span),
+                    new_text: edit.text,
+                })
+                .collect(),
+        )
+    }
+
+    /// Reindents the line the cursor is on after a newline is typed.
+    fn on_type_formatting(&self, params: DocumentOnTypeFormattingParams) -> Option<Vec<TextEdit>> {
+        let position = params.text_document_position;
+        let Document { text: src, config } = self.documents.get(&position.text_document.uri)?;
+        let config = config.as_ref()?;
+        let line_index = LineIndex::new(src);
+
+        // A line past the end of the document has nothing to reindent
+        let Some(line_range) = line_index.line_range(position.position.line as usize) else {
+            return Some(vec![]);
+        };
+        let line_start = line_range.start;
+        let indentation_end = src[line_range.clone()]
+            .find(|c: char| c != ' ' && c != '\t')
+            .map(|i| line_start + i)
+            .unwrap_or(line_range.end);
+
+        let indent = indent_for_newline(src, line_start, config)?;
+        let indentation = config.indentation(indent);
+
+        if src[line_start..indentation_end] == indentation {
+            return Some(vec![]);
+        }
+
+        Some(vec![TextEdit {
+            range: to_range(&line_index, (line_start..indentation_end).into()),
+            new_text: indentation,
+        }])
+    }
+}
+
+/// Returns the path of a `file:` URI.
+fn file_path(uri: &Uri) -> Option<PathBuf> {
+    if uri.scheme()?.as_str() != "file" {
+        return None;
+    }
+
+    let path = uri.path().as_estr().decode().into_string().ok()?;
+    Some(PathBuf::from(path.as_ref()))
+}
+
+fn extract<R: RequestTrait>(request: Request) -> Result<R::Params, String> {
+    request
+        .extract(R::METHOD)
+        .map(|(_, params): (RequestId, R::Params)| params)
+        .map_err(|e| e.to_string())
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> Result<serde_json::Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+fn to_position(line_index: &LineIndex, offset: usize) -> Position {
+    let pos = line_index.line_col(offset, Encoding::Utf16);
+    Position::new(pos.line as u32, pos.column as u32)
+}
+
+fn to_range(line_index: &LineIndex, span: Span) -> Range {
+    Range::new(
+        to_position(line_index, span.start),
+        to_position(line_index, span.end),
+    )
+}
+
+fn from_position(line_index: &LineIndex, position: Position) -> usize {
+    let pos = LineCol {
+        line: position.line as usize,
+        column: position.character as usize,
+    };
+    line_index.offset(pos, Encoding::Utf16)
+}
+
+fn from_range(line_index: &LineIndex, range: Range) -> Span {
+    (from_position(line_index, range.start)..from_position(line_index, range.end)).into()
+}
@@ -1,34 +1,585 @@
 use std::{
-    io::{self, Read},
+    collections::HashMap,
+    env, fs,
+    io::{self, Read, Write},
+    num::NonZeroUsize,
+    path::{self, Path, PathBuf},
     process::exit,
 };
 
-use crate::format::format_text;
-
-mod doc;
-mod doc_ext;
-mod error;
-mod format;
-mod kind;
-mod lexer;
-mod node;
-mod parser;
-mod peekable_ext;
-
-fn read_stdin() -> Result<String, io::Error> {
-    let mut buf = String::new();
-    io::stdin().read_to_string(&mut buf)?;
+use clap::{Args, Parser, Subcommand};
+
+use lispfmt::{
+    config::{self, Bom, Config, EndOfLine, IndentStyle, Options},
+    diagnostic::{Diagnostic, Emitter, ErrorFormat},
+    directive, editorconfig,
+    error_code::ErrorCode,
+    format::format_text,
+    indent::indent_for_newline,
+    line_index::LineIndex,
+    lsp,
+    node::Span,
+    parinfer::{Mode, parinfer},
+    parser::parse,
+    utf8::{self, Decoded},
+    verify::{check_equivalence, verify},
+};
+
+/// A universal formatter for the Lisp family of programming languages.
+#[derive(Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Files to format. Reads from stdin when no files are given.
+    files: Vec<PathBuf>,
+
+    /// Report files that would be reformatted instead of printing the formatted output.
+    #[arg(long)]
+    check: bool,
+
+    /// How errors and check results are reported.
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human, global = true)]
+    error_format: ErrorFormat,
+
+    /// Write the formatted output back to the files instead of printing it.
+    #[arg(long, conflicts_with = "check")]
+    write: bool,
+
+    /// Write files even if the formatted output does not contain the same code as the input.
+    #[arg(long, requires = "write")]
+    no_equivalence_check: bool,
+
+    /// Format files that are not valid UTF-8, leaving the invalid sequences unchanged.
+    #[arg(long)]
+    lossy: bool,
+
+    /// Check that the formatted output contains the same code as the input and does not change
+    /// when formatted again.
+    #[arg(long)]
+    verify: bool,
+
+    #[command(flatten)]
+    format: FormatArgs,
+}
+
+/// Options that control the formatted output. They take precedence over those of a
+/// `lispfmt.toml` file, which in turn take precedence over those of `.editorconfig` files.
+#[derive(Args)]
+struct FormatArgs {
+    /// The line ending of the formatted output [default: auto].
+    #[arg(long, global = true, value_enum)]
+    end_of_line: Option<EndOfLine>,
+
+    /// Keep or remove a byte-order mark at the start of the input [default: keep].
+    #[arg(long, global = true, value_enum)]
+    bom: Option<Bom>,
+
+    /// End the output with a line ending even if the input has none.
+    #[arg(long, global = true, overrides_with = "no_insert_final_newline")]
+    insert_final_newline: bool,
+
+    /// Only end the output with a line ending if the input has one.
+    #[arg(long, global = true, overrides_with = "insert_final_newline")]
+    no_insert_final_newline: bool,
+
+    /// The most consecutive blank lines kept between top-level forms [default: 1].
+    #[arg(long, global = true)]
+    max_blank_lines: Option<usize>,
+
+    /// The most consecutive blank lines kept inside a form [default: 1].
+    #[arg(long, global = true)]
+    max_inner_blank_lines: Option<usize>,
+
+    /// The number of blank lines required around top-level definitions [default: 0].
+    #[arg(long, global = true)]
+    blank_lines_between_definitions: Option<usize>,
+
+    /// The number of columns taken up by characters of ambiguous East Asian width [default: 1].
+    #[arg(long, global = true, value_parser = clap::value_parser!(u8).range(1..=2))]
+    ambiguous_width: Option<u8>,
+
+    /// Indent with spaces or tabs [default: spaces].
+    #[arg(long, global = true, value_enum)]
+    indent_style: Option<IndentStyle>,
+
+    /// The number of columns a tab advances to [default: 8].
+    #[arg(long, global = true)]
+    tab_width: Option<NonZeroUsize>,
+
+    /// How far the contents of a list are indented [default: 2].
+    #[arg(long, global = true)]
+    list_indent: Option<usize>,
+
+    /// How far the contents of a sequence are indented [default: 1].
+    #[arg(long, global = true)]
+    sequence_indent: Option<usize>,
+
+    /// How far the contents of a table are indented [default: 1].
+    #[arg(long, global = true)]
+    table_indent: Option<usize>,
+
+    /// Align the values of table entries, bindings and clauses into a column.
+    #[arg(long, global = true, overrides_with = "no_align")]
+    align: bool,
+
+    /// Do not align table entries, bindings and clauses.
+    #[arg(long, global = true, overrides_with = "align")]
+    no_align: bool,
+
+    /// Align the trailing comments of consecutive lines into a column.
+    #[arg(long, global = true, overrides_with = "no_align_comments")]
+    align_comments: bool,
+
+    /// Do not align trailing comments.
+    #[arg(long, global = true, overrides_with = "align_comments")]
+    no_align_comments: bool,
+
+    /// The column aligned trailing comments start at or after [default: 0].
+    #[arg(long, global = true)]
+    comment_min_column: Option<usize>,
+
+    /// The most spaces added in front of a trailing comment to align it [default: 20].
+    #[arg(long, global = true)]
+    comment_max_padding: Option<usize>,
+
+    /// Normalize the semicolons of comments by their position and put a single space after them.
+    #[arg(long, global = true, overrides_with = "no_normalize_comments")]
+    normalize_comments: bool,
+
+    /// Keep the semicolons and spacing of comments.
+    #[arg(long, global = true, overrides_with = "normalize_comments")]
+    no_normalize_comments: bool,
+
+    /// Reflow runs of comments on their own lines to fill the line width.
+    #[arg(long, global = true, overrides_with = "no_reflow_comments")]
+    reflow_comments: bool,
+
+    /// Keep the lines of comments as they are.
+    #[arg(long, global = true, overrides_with = "reflow_comments")]
+    no_reflow_comments: bool,
+
+    /// Reindent the continuation lines of docstrings when their form moves.
+    #[arg(long, global = true, overrides_with = "no_docstrings")]
+    docstrings: bool,
+
+    /// Keep docstrings as they are.
+    #[arg(long, global = true, overrides_with = "docstrings")]
+    no_docstrings: bool,
+
+    /// Wrap the paragraphs of docstrings to the line width. Requires `--docstrings`.
+    #[arg(long, global = true, overrides_with = "no_wrap_docstrings")]
+    wrap_docstrings: bool,
+
+    /// Do not wrap docstrings.
+    #[arg(long, global = true, overrides_with = "wrap_docstrings")]
+    no_wrap_docstrings: bool,
+}
+
+impl FormatArgs {
+    /// Returns the options given on the command line. Flags that are not given are left unset, so
+    /// that they do not override configuration files.
+    fn options(&self) -> Options {
+        /// Returns the value of a flag and its `--no-` counterpart, of which only the last given is set.
+        fn flag(yes: bool, no: bool) -> Option<bool> {
+            match (yes, no) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            }
+        }
+
+        Options {
+            end_of_line: self.end_of_line,
+            bom: self.bom,
+            insert_final_newline: flag(self.insert_final_newline, self.no_insert_final_newline),
+            max_blank_lines: self.max_blank_lines,
+            max_inner_blank_lines: self.max_inner_blank_lines,
+            blank_lines_between_definitions: self.blank_lines_between_definitions,
+            ambiguous_width: self.ambiguous_width.map(usize::from),
+            indent_style: self.indent_style,
+            tab_width: self.tab_width,
+            list_indent: self.list_indent,
+            sequence_indent: self.sequence_indent,
+            table_indent: self.table_indent,
+            align: flag(self.align, self.no_align),
+            align_comments: flag(self.align_comments, self.no_align_comments),
+            comment_min_column: self.comment_min_column,
+            comment_max_padding: self.comment_max_padding,
+            normalize_comments: flag(self.normalize_comments, self.no_normalize_comments),
+            reflow_comments: flag(self.reflow_comments, self.no_reflow_comments),
+            docstrings: flag(self.docstrings, self.no_docstrings),
+            wrap_docstrings: flag(self.wrap_docstrings, self.no_wrap_docstrings),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print a detailed description of an error code, or list all error codes.
+    Explain {
+        /// The error code to explain, e.g. `LF001`.
+        code: Option<ErrorCode>,
+    },
+    /// Print the column a new line inserted at a byte offset of stdin should be indented to.
+    Indent {
+        /// The byte offset the new line is inserted at.
+        offset: usize,
+    },
+    /// Run a language server over stdio.
+    Lsp,
+    /// Repair the delimiters or indentation of stdin like Parinfer and print the result.
+    Parinfer {
+        /// Whether closing delimiters are inferred from indentation or the other way around.
+        #[arg(long, value_enum, default_value_t = Mode::Indent)]
+        mode: Mode,
+    },
+    /// Print the syntax tree of stdin with the kind and byte span of every element.
+    Tree {
+        /// Print the tree as JSON instead of an indented outline.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+fn explain(code: Option<ErrorCode>) {
+    match code {
+        Some(code) => print!("{}: {}\n\n{}", code, code.title(), code.explanation()),
+        None => ErrorCode::ALL
+            .iter()
+            .for_each(|code| println!("{}: {}", code, code.title())),
+    }
+}
+
+fn read_stdin() -> Result<Vec<u8>, io::Error> {
+    let mut buf = vec![];
+    io::stdin().read_to_end(&mut buf)?;
     Ok(buf)
 }
 
+/// Decodes the contents of a file as UTF-8, reporting the first invalid sequence otherwise.
+fn decode_input(file: &str, bytes: Vec<u8>, lossy: bool, emitter: &mut Emitter) -> Option<Decoded> {
+    if lossy {
+        return Some(utf8::decode_lossy(&bytes));
+    }
+
+    utf8::decode(bytes)
+        .map(Decoded::from)
+        .map_err(|error| {
+            emitter.emit(file, &LineIndex::new(&error.text), &error.diagnostic());
+        })
+        .ok()
+}
+
+/// Decodes stdin for a subcommand, exiting if it is not valid UTF-8.
+fn decode_stdin(error_format: ErrorFormat) -> String {
+    let mut emitter = Emitter::new(error_format);
+    let decoded = read_stdin()
+        .map_err(|error| emit_io_error(&mut emitter, "<stdin>", "read", &error))
+        .ok()
+        .and_then(|bytes| decode_input("<stdin>", bytes, false, &mut emitter));
+
+    let Some(decoded) = decoded else {
+        emitter.finish();
+        exit(1)
+    };
+    decoded.text
+}
+
+/// Resolves the configuration of each formatted file, reading every `lispfmt.toml` file once.
+struct Configs<'a> {
+    args: &'a FormatArgs,
+    files: HashMap<PathBuf, Option<Options>>,
+}
+
+impl<'a> Configs<'a> {
+    fn new(args: &'a FormatArgs) -> Self {
+        Self {
+            args,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Returns the configuration for formatting `path`, or stdin in the current directory.
+    ///
+    /// Returns `None` if the `lispfmt.toml` file that applies is invalid, or if the merged options
+    /// are.
+    fn resolve(&mut self, path: Option<&Path>, emitter: &mut Emitter) -> Option<Config> {
+        let mut config = Config::default();
+        if let Some(path) = path {
+            editorconfig::options(path).apply(&mut config);
+        }
+
+        let dir = match path {
+            Some(path) => path::absolute(path)
+                .ok()
+                .and_then(|path| path.parent().map(Path::to_path_buf)),
+            None => env::current_dir().ok(),
+        };
+        let file = dir.and_then(|dir| config::find_file(&dir));
+        if let Some(file) = &file {
+            let options = self
+                .files
+                .entry(file.clone())
+                .or_insert_with_key(|file| load_options(file, emitter));
+            options.as_ref()?.apply(&mut config);
+        }
+
+        self.args.options().apply(&mut config);
+
+        if let Err(message) = config.validate() {
+            // The options may come from any of the sources, so the error is not shown in one
+            let name = match &file {
+                Some(file) => file.display().to_string(),
+                None => "<command line>".to_string(),
+            };
+            let diagnostic = Diagnostic::error(ErrorCode::InvalidConfig, message, (0..0).into());
+            emitter.emit(&name, &LineIndex::new(""), &diagnostic);
+            return None;
+        }
+
+        Some(config)
+    }
+
+    /// Returns the configuration for a subcommand reading stdin, exiting if it is invalid.
+    fn resolve_stdin(&mut self, error_format: ErrorFormat) -> Config {
+        let mut emitter = Emitter::new(error_format);
+        self.resolve(None, &mut emitter).unwrap_or_else(|| {
+            emitter.finish();
+            exit(1)
+        })
+    }
+}
+
+/// Reports an error reading or writing a file, which has no position in its contents.
+fn emit_io_error(emitter: &mut Emitter, file: &str, action: &str, error: &io::Error) {
+    let message = format!("unable to {} {}: {}", action, file, error);
+    let diagnostic = Diagnostic::error(ErrorCode::Io, message, (0..0).into());
+    emitter.emit(file, &LineIndex::new(""), &diagnostic);
+}
+
+/// Reads a `lispfmt.toml` file, reporting it if it cannot be read or is invalid.
+fn load_options(file: &Path, emitter: &mut Emitter) -> Option<Options> {
+    let text = fs::read_to_string(file)
+        .map_err(|error| emit_io_error(emitter, &file.display().to_string(), "read", &error))
+        .ok()?;
+
+    Options::from_toml(&text)
+        .map_err(|error| {
+            let span = error.span().unwrap_or(0..0);
+            let diagnostic =
+                Diagnostic::error(ErrorCode::InvalidConfig, error.message(), span.into());
+            emitter.emit(
+                &file.display().to_string(),
+                &LineIndex::new(&text),
+                &diagnostic,
+            );
+        })
+        .ok()
+}
+
+/// Returns the span of the first character that differs between the input and the formatted output.
+fn first_difference(src: &str, formatted: &str) -> Option<Span> {
+    let pos = src
+        .char_indices()
+        .zip(formatted.chars())
+        .find(|((_, a), b)| a != b)
+        .map(|((i, _), _)| i)
+        .or_else(|| (src.len() != formatted.len()).then(|| src.len().min(formatted.len())))?;
+    let end = src[pos..]
+        .chars()
+        .next()
+        .map(|c| pos + c.len_utf8())
+        .unwrap_or(pos);
+
+    Some((pos..end).into())
+}
+
 fn main() {
-    let input = read_stdin().unwrap_or_else(|e| panic!("Unable to read input from stdin: {}", e));
+    let cli = Cli::parse();
+    let mut configs = Configs::new(&cli.format);
+
+    match cli.command {
+        Some(Command::Explain { code }) => return explain(code),
+        Some(Command::Indent { offset }) => {
+            let input = decode_stdin(cli.error_format);
+            let config = configs.resolve_stdin(cli.error_format);
+
+            match indent_for_newline(&input, offset, &config) {
+                Some(column) => println!("{}", column),
+                None => {
+                    let message = format!(
+                        "unable to compute the indentation at offset {}: the text before it ends \
+                         inside a token or contains an invalid one",
+                        offset
+                    );
+                    let offset = offset.min(input.len());
+                    let diagnostic = Diagnostic::error(
+                        ErrorCode::InvalidToken,
+                        message,
+                        (offset..offset).into(),
+                    );
+                    let mut emitter = Emitter::new(cli.error_format);
+                    emitter.emit("<stdin>", &LineIndex::new(&input), &diagnostic);
+                    emitter.finish();
+                    exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Lsp) => {
+            // Configuration files may change while the server runs, so they are read again for
+            // every opened document
+            let resolve = |path: Option<&Path>| {
+                let mut emitter = Emitter::new(cli.error_format);
+                let config = Configs::new(&cli.format).resolve(path, &mut emitter);
+                emitter.finish();
+                config
+            };
+            if let Err(error) = lsp::run(resolve) {
+                eprintln!("Language server error: {}", error);
+                exit(1);
+            }
+            return;
+        }
+        Some(Command::Parinfer { mode }) => {
+            let input = decode_stdin(cli.error_format);
+
+            match parinfer(&input, mode) {
+                Ok(repaired) => print!("{}", repaired),
+                Err(error) => {
+                    let line_index = LineIndex::new(&input);
+                    let mut emitter = Emitter::new(cli.error_format);
+                    for diagnostic in error.diagnostics() {
+                        emitter.emit("<stdin>", &line_index, &diagnostic);
+                    }
+                    emitter.finish();
+                    exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Tree { json }) => {
+            let input = decode_stdin(cli.error_format);
+
+            match parse(&input) {
+                Ok(tree) if json => println!("{:#}", tree.to_json()),
+                Ok(tree) => println!("{:?}", tree),
+                Err(error) => {
+                    let line_index = LineIndex::new(&input);
+                    let mut emitter = Emitter::new(cli.error_format);
+                    for diagnostic in error.diagnostics() {
+                        emitter.emit("<stdin>", &line_index, &diagnostic);
+                    }
+                    emitter.finish();
+                    exit(1);
+                }
+            }
+            return;
+        }
+        None => (),
+    }
+
+    let mut emitter = Emitter::new(cli.error_format);
+    let mut failed = false;
+
+    let inputs = if cli.files.is_empty() {
+        vec![("<stdin>".to_string(), None, read_stdin())]
+    } else {
+        cli.files
+            .iter()
+            .map(|path| (path.display().to_string(), Some(path), fs::read(path)))
+            .collect()
+    };
+    let inputs = inputs
+        .into_iter()
+        .filter_map(|(file, path, bytes)| {
+            let bytes = bytes
+                .map_err(|error| emit_io_error(&mut emitter, &file, "read", &error))
+                .ok();
+            let input = bytes.and_then(|bytes| decode_input(&file, bytes, cli.lossy, &mut emitter));
+            failed |= input.is_none();
+            input.map(|input| (file, path, input))
+        })
+        .collect::<Vec<_>>();
+
+    for (file, path, decoded) in &inputs {
+        let input = &decoded.text;
+        let Some(config) = configs.resolve(path.map(PathBuf::as_path), &mut emitter) else {
+            failed = true;
+            continue;
+        };
+        let line_index = LineIndex::new(input);
+
+        if let Ok(tree) = parse(input) {
+            for warning in directive::warnings(&tree) {
+                emitter.emit(file, &line_index, &warning);
+            }
+        }
+
+        let result = format_text(input, &config);
 
-    match format_text(&input) {
-        Ok(formatted) => print!("{}", formatted),
-        Err(error) => {
-            error.print(&input);
-            exit(1);
+        if let (true, Ok(formatted)) = (cli.verify, &result)
+            && let Err(error) = verify(input, formatted, &config)
+        {
+            emitter.emit(file, &line_index, &error.diagnostic(formatted));
+            failed = true;
+            continue;
         }
+
+        match result {
+            Ok(formatted) if cli.check => {
+                if let Some(span) = first_difference(input, &formatted) {
+                    let diagnostic = Diagnostic::warning(
+                        ErrorCode::Unformatted,
+                        "file would be reformatted",
+                        span,
+                    );
+                    emitter.emit(file, &line_index, &diagnostic);
+                    failed = true;
+                }
+            }
+            Ok(formatted) if cli.write && path.is_some() => {
+                let check = match cli.no_equivalence_check {
+                    true => Ok(()),
+                    false => check_equivalence(input, &formatted, &config),
+                };
+
+                match check {
+                    Ok(()) if formatted != *input => {
+                        let path = path.expect("Path should be present.");
+                        if let Err(error) = fs::write(path, decoded.encode(&formatted)) {
+                            emit_io_error(&mut emitter, file, "write", &error);
+                            failed = true;
+                        }
+                    }
+                    Ok(()) => (),
+                    Err(error) => {
+                        emitter.emit(file, &line_index, &error.diagnostic(&formatted));
+                        failed = true;
+                    }
+                }
+            }
+            Ok(formatted) => {
+                if let Err(error) = io::stdout().write_all(&decoded.encode(&formatted)) {
+                    emit_io_error(&mut emitter, "<stdout>", "write", &error);
+                    failed = true;
+                }
+            }
+            Err(error) => {
+                for diagnostic in error.diagnostics() {
+                    emitter.emit(file, &line_index, &diagnostic);
+                }
+                failed = true;
+            }
+        }
+    }
+
+    emitter.finish();
+
+    if failed {
+        exit(1);
     }
 }
@@ -1,4 +1,5 @@
 use chumsky::span::SimpleSpan;
+use serde_json::{Value, json};
 
 use crate::kind::SyntaxKind;
 
@@ -98,6 +99,24 @@ impl SyntaxElement<'_> {
             SyntaxElement::Node(node) => node.span,
         }
     }
+
+    /// Returns the tree as JSON, with the kind and byte span of every element, the text of tokens
+    /// and the children of nodes.
+    pub fn to_json(&self) -> Value {
+        let span = self.span();
+        match self {
+            SyntaxElement::Token(token) => json!({
+                "kind": format!("{:?}", token.kind),
+                "span": { "start": span.start, "end": span.end },
+                "text": token.text,
+            }),
+            SyntaxElement::Node(node) => json!({
+                "kind": format!("{:?}", node.kind),
+                "span": { "start": span.start, "end": span.end },
+                "children": node.children.iter().map(|c| c.to_json()).collect::<Vec<_>>(),
+            }),
+        }
+    }
 }
 
 #[derive(Debug)]
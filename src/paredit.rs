@@ -0,0 +1,368 @@
+use crate::{
+    config::Config,
+    error::Error,
+    format::{TextEdit, format_range},
+    kind::SyntaxKind,
+    node::{Span, SyntaxElement},
+    parser::parse,
+};
+
+/// A structural editing operation, applied relative to a cursor offset.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Operation {
+    /// Moves the closing delimiter of the innermost container with a following expression past
+    /// that expression: `(a |b) c` becomes `(a |b c)`.
+    Slurp,
+    /// Moves the last expression of the innermost container out of it: `(a |b c)` becomes
+    /// `(a |b) c`.
+    Barf,
+    /// Removes the delimiters of the innermost container: `(a (|b c))` becomes `(a |b c)`.
+    Splice,
+    /// Replaces the innermost container with the expression at the cursor: `(a (b |c))` becomes
+    /// `(a |c)`.
+    Raise,
+    /// Wraps the expression at the cursor in a list: `(a |b)` becomes `(a (|b))`.
+    Wrap,
+    /// Splits the innermost container at the cursor: `(a b| c)` becomes `(a b)| (c)`.
+    Split,
+    /// Joins the containers before and after the cursor: `(a b) |(c d)` becomes `(a b| c d)`.
+    Join,
+    /// Swaps the expressions before and after the cursor: `(a b |c)` becomes `(a c b|)`.
+    Transpose,
+    /// Deletes the expression at or after the cursor: `(a |b c)` becomes `(a | c)`.
+    Kill,
+}
+
+/// The result of a structural edit.
+#[derive(Debug, PartialEq)]
+pub struct Edit {
+    /// The edit to apply to the source text.
+    pub edit: TextEdit,
+    /// The cursor offset in the source text after the edit has been applied.
+    pub cursor: usize,
+}
+
+/// Applies `operation` at `cursor` and reformats the top-level expressions it touched.
+///
+/// Returns `None` if the operation is not applicable at the cursor, e.g. when slurping with
+/// nothing to slurp, and an [`Error::InvalidEdit`] if it would make the source unparseable, e.g.
+/// when barfing the value of a table key.
+pub fn paredit<'src>(
+    src: &'src str,
+    operation: Operation,
+    cursor: usize,
+    config: &Config,
+) -> Result<Option<Edit>, Error<'src>> {
+    let tree = parse(src)?;
+
+    let Some(raw) = raw_edit(src, &tree, operation, cursor) else {
+        return Ok(None);
+    };
+
+    let mut edited = src.to_string();
+    edited.replace_range(raw.edit.span.into_range(), &raw.edit.text);
+    let changed = Span::from(raw.edit.span.start..raw.edit.span.start + raw.edit.text.len());
+
+    let (edited, cursor) = match format_range(&edited, changed, config) {
+        Ok(Some(format_edit)) => {
+            let mut formatted = edited.clone();
+            formatted.replace_range(format_edit.span.into_range(), &format_edit.text);
+            let cursor = map_cursor(&edited, &formatted, raw.cursor);
+            (formatted, cursor)
+        }
+        Ok(None) => (edited, raw.cursor),
+        Err(Error::Internal(message)) => return Err(Error::Internal(message)),
+        Err(_) => return Err(Error::InvalidEdit(raw.edit.span)),
+    };
+
+    Ok(Some(Edit {
+        edit: diff(src, &edited),
+        cursor,
+    }))
+}
+
+fn raw_edit(src: &str, root: &SyntaxElement, operation: Operation, cursor: usize) -> Option<Edit> {
+    let containers = containers_at(root, cursor);
+    let innermost = containers.last().copied();
+    let siblings = exprs(innermost.unwrap_or(root));
+
+    let before = siblings.iter().rev().find(|e| e.span().end <= cursor);
+    let after = siblings.iter().find(|e| e.span().start >= cursor);
+    let at = siblings.iter().find(|e| e.span().end > cursor);
+
+    let edit = |span: Span, text: String, cursor: usize| {
+        Some(Edit {
+            edit: TextEdit { span, text },
+            cursor,
+        })
+    };
+
+    match operation {
+        Operation::Slurp => {
+            // Find the innermost container that has an expression after it
+            let (container, next) = containers.iter().enumerate().rev().find_map(|(i, c)| {
+                let parent = if i == 0 { root } else { containers[i - 1] };
+                let next = exprs(parent)
+                    .into_iter()
+                    .find(|e| e.span().start >= c.span().end)?;
+                Some((*c, next))
+            })?;
+            let close = closing(container);
+
+            edit(
+                (close.span().start..next.span().end).into(),
+                format!(
+                    "{}{}",
+                    &src[close.span().end..next.span().end],
+                    close.text()
+                ),
+                cursor,
+            )
+        }
+        Operation::Barf => {
+            let container = innermost?;
+            let (open, close) = (opening(container), closing(container));
+            let last = exprs(container).pop()?;
+            let previous_end = exprs(container)
+                .iter()
+                .rev()
+                .nth(1)
+                .map(|e| e.span().end)
+                .unwrap_or(open.span().end);
+
+            let separator = if previous_end == last.span().start {
+                " "
+            } else {
+                ""
+            };
+            let text = format!(
+                "{}{}{}",
+                close.text(),
+                separator,
+                &src[previous_end..close.span().start]
+            );
+            let cursor = if cursor > previous_end {
+                cursor + close.text().len() + separator.len()
+            } else {
+                cursor
+            };
+
+            edit((previous_end..close.span().end).into(), text, cursor)
+        }
+        Operation::Splice => {
+            let container = innermost?;
+            let (open, close) = (opening(container), closing(container));
+
+            edit(
+                (open.span().start..close.span().end).into(),
+                src[open.span().end..close.span().start].to_string(),
+                cursor - open.text().len(),
+            )
+        }
+        Operation::Raise => {
+            let container = innermost?;
+            let expr = at?;
+            let offset = cursor.saturating_sub(expr.span().start);
+
+            edit(
+                container.span(),
+                src[expr.span().into_range()].to_string(),
+                container.span().start + offset,
+            )
+        }
+        Operation::Wrap => {
+            let expr = at?;
+
+            edit(
+                expr.span(),
+                format!("({})", &src[expr.span().into_range()]),
+                expr.span().start + 1,
+            )
+        }
+        Operation::Split => {
+            let container = innermost?;
+            let (open, close) = (opening(container), closing(container));
+            // A cursor inside an expression splits after it, so it stays in the first container
+            let split = at
+                .filter(|e| e.span().start < cursor)
+                .map_or(cursor, |e| e.span().end);
+            let before = siblings.iter().rev().find(|e| e.span().end <= split)?;
+            let after = siblings.iter().find(|e| e.span().start >= split)?;
+
+            edit(
+                (before.span().end..after.span().start).into(),
+                format!(
+                    "{}{}{}",
+                    close.text(),
+                    &src[before.span().end..after.span().start],
+                    open.text()
+                ),
+                before.span().end + close.text().len(),
+            )
+        }
+        Operation::Join => {
+            let (before, after) = (before?, after?);
+            if before.kind() != after.kind() || !is_container(before.kind()) {
+                return None;
+            }
+
+            let (close, open) = (closing(before), opening(after));
+            let between = &src[close.span().end..open.span().start];
+            let separator = if between.is_empty() { " " } else { between };
+
+            edit(
+                (close.span().start..open.span().end).into(),
+                separator.to_string(),
+                close.span().start,
+            )
+        }
+        Operation::Transpose => {
+            let (before, after) = (before?, after?);
+            let text = format!(
+                "{}{}{}",
+                &src[after.span().into_range()],
+                &src[before.span().end..after.span().start],
+                &src[before.span().into_range()]
+            );
+
+            edit(
+                (before.span().start..after.span().end).into(),
+                text,
+                after.span().end,
+            )
+        }
+        Operation::Kill => {
+            let expr = at?;
+
+            edit(expr.span(), String::new(), cursor.min(expr.span().start))
+        }
+    }
+}
+
+/// Returns the containers the cursor is inside of, outermost first.
+fn containers_at<'a>(root: &'a SyntaxElement<'a>, cursor: usize) -> Vec<&'a SyntaxElement<'a>> {
+    let mut containers = vec![];
+    let mut elem = root;
+
+    while let Some(child) = elem.children().find(|c| {
+        let span = c.span();
+        match c.kind() {
+            kind if is_container(kind) => span.start < cursor && cursor < span.end,
+            SyntaxKind::Pair | SyntaxKind::Prefixed => span.start <= cursor && cursor <= span.end,
+            _ => false,
+        }
+    }) {
+        if is_container(child.kind()) {
+            containers.push(child);
+        }
+        elem = child;
+    }
+
+    containers
+}
+
+/// Returns the expressions inside a container or the root, with key-value pairs flattened.
+fn exprs<'a>(elem: &'a SyntaxElement<'a>) -> Vec<&'a SyntaxElement<'a>> {
+    let children = elem.children().filter(|e| !e.kind().is_trivia());
+    let children: Vec<_> = if *elem.kind() == SyntaxKind::Root {
+        children.collect()
+    } else {
+        let children: Vec<_> = children.collect();
+        children[1..children.len() - 1].to_vec()
+    };
+
+    children
+        .into_iter()
+        .flat_map(|e| match e.kind() {
+            SyntaxKind::Pair => e.children().filter(|e| !e.kind().is_trivia()).collect(),
+            _ => vec![e],
+        })
+        .collect()
+}
+
+fn is_container(kind: &SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::List | SyntaxKind::Sequence | SyntaxKind::Table
+    )
+}
+
+fn opening<'a>(container: &'a SyntaxElement<'a>) -> &'a SyntaxElement<'a> {
+    container
+        .children()
+        .next()
+        .expect("Container should have an opening delimiter.")
+}
+
+fn closing<'a>(container: &'a SyntaxElement<'a>) -> &'a SyntaxElement<'a> {
+    container
+        .children()
+        .last()
+        .expect("Container should have a closing delimiter.")
+}
+
+/// Maps a cursor from `before` to `after`, where the two texts only differ in whitespace, by
+/// keeping the same number of non-whitespace characters in front of it.
+fn map_cursor(before: &str, after: &str, cursor: usize) -> usize {
+    let preceding = before[..cursor]
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .count();
+    let attached = before[..cursor]
+        .chars()
+        .next_back()
+        .is_none_or(|c| !c.is_whitespace());
+
+    let mut count = 0;
+    for (i, c) in after.char_indices() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if count == preceding {
+            // Keep the cursor right after the previous character if it was attached to it
+            return if attached {
+                after[..i].trim_end().len()
+            } else {
+                i
+            };
+        }
+        count += 1;
+    }
+
+    if attached {
+        after.trim_end().len()
+    } else {
+        after.len()
+    }
+}
+
+/// Computes a single edit that turns `before` into `after`.
+fn diff(before: &str, after: &str) -> TextEdit {
+    let mut prefix = before
+        .bytes()
+        .zip(after.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(prefix) || !after.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+
+    let max_suffix = before.len().min(after.len()) - prefix;
+    let mut suffix = before
+        .bytes()
+        .rev()
+        .zip(after.bytes().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(before.len() - suffix)
+        || !after.is_char_boundary(after.len() - suffix)
+    {
+        suffix -= 1;
+    }
+
+    TextEdit {
+        span: (prefix..before.len() - suffix).into(),
+        text: after[prefix..after.len() - suffix].to_string(),
+    }
+}
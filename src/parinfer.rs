@@ -0,0 +1,273 @@
+use clap::ValueEnum;
+
+use crate::{
+    error::{Error, ParseError},
+    kind::SyntaxKind,
+    lexer::lex,
+    line_index::{Encoding, LineIndex},
+    node::Token,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+pub enum Mode {
+    /// Infer closing delimiters from indentation
+    Indent,
+    /// Correct indentation from delimiters
+    Paren,
+}
+
+/// Repairs `src` the way Parinfer does in the given mode.
+pub fn parinfer<'src>(src: &'src str, mode: Mode) -> Result<String, Error<'src>> {
+    match mode {
+        Mode::Indent => indent_mode(src),
+        Mode::Paren => paren_mode(src),
+    }
+}
+
+/// Rewrites the closing delimiters at the end of each line so the structure of the code follows
+/// its indentation.
+///
+/// Closing delimiters at the start or end of a line are removed and reinserted at the end of the
+/// last line whose forms are still open. Closing delimiters in the middle of a line are kept if
+/// they match an open form and removed otherwise.
+pub fn indent_mode<'src>(src: &'src str) -> Result<String, Error<'src>> {
+    let tokens = lex(src)?;
+    let line_index = LineIndex::new(src);
+    let mut edits = Edits::default();
+
+    // Open forms as their opening delimiter kind and column
+    let mut stack: Vec<(SyntaxKind, usize)> = vec![];
+    let mut trail_start = None;
+
+    for line in code_lines(&tokens, &line_index) {
+        let leading = line.iter().take_while(|t| is_closing(t.kind)).count();
+        let trailing = line.iter().rev().take_while(|t| is_closing(t.kind)).count();
+
+        if leading == line.len() {
+            line.iter().for_each(|t| edits.remove(t));
+            continue;
+        }
+
+        let (before, rest) = line.split_at(leading);
+        let (code, trail) = rest.split_at(rest.len() - trailing);
+        before.iter().for_each(|t| edits.remove(t));
+        // The whitespace before the trail is removed along with it
+        if let (Some(code_end), Some(last)) = (code.last(), trail.last()) {
+            edits.replace(code_end.span.end, last.span.end, String::new());
+        }
+
+        let indentation = line_index
+            .line_col(code[0].span.start, Encoding::Char)
+            .column;
+        let mut closers = String::new();
+        while let Some(&(open, _)) = stack.last().filter(|(_, col)| *col >= indentation) {
+            closers.push_str(closing_text(open));
+            stack.pop();
+        }
+        if let Some(offset) = trail_start {
+            edits.insert(offset, closers);
+        }
+
+        for token in code {
+            if is_opening(token.kind) {
+                let column = line_index.line_col(token.span.start, Encoding::Char).column;
+                stack.push((token.kind, column));
+            } else if is_closing(token.kind) {
+                if stack.last().map(|(open, _)| closing_kind(*open)) == Some(token.kind) {
+                    stack.pop();
+                } else {
+                    edits.remove(token);
+                }
+            }
+        }
+
+        trail_start = code.last().map(|t| t.span.end);
+    }
+
+    if let Some(offset) = trail_start {
+        let closers = stack
+            .iter()
+            .rev()
+            .map(|(open, _)| closing_text(*open))
+            .collect();
+        edits.insert(offset, closers);
+    }
+
+    Ok(edits.apply(src))
+}
+
+/// Reindents lines so that each line is inside the form it belongs to according to its
+/// delimiters, and is not indented past a form closed at the end of the previous line.
+///
+/// Closing delimiters that do not close an open form and forms that are still open at the end of
+/// the input are reported as errors, since the structure they belong to cannot be inferred from
+/// them.
+pub fn paren_mode<'src>(src: &'src str) -> Result<String, Error<'src>> {
+    let tokens = lex(src)?;
+    let line_index = LineIndex::new(src);
+    let mut edits = Edits::default();
+    let mut errors = vec![];
+
+    // Open forms as their opening delimiter and its column in the output
+    let mut stack: Vec<(&Token, usize)> = vec![];
+    // The column of the outermost form closed at the end of the previous line
+    let mut max_indentation = None;
+
+    for line in code_lines(&tokens, &line_index) {
+        let first = line[0];
+        let start = line_index.line_col(first.span.start, Encoding::Char);
+        let line_start = line_index
+            .line_range(start.line)
+            .expect("Token should be on a line.")
+            .start;
+
+        let min_indentation = stack.last().map(|(_, col)| col + 1).unwrap_or(0);
+        let indentation = if is_closing(first.kind) {
+            start.column
+        } else {
+            start
+                .column
+                .min(max_indentation.unwrap_or(usize::MAX))
+                .max(min_indentation)
+        };
+
+        if indentation != start.column {
+            edits.replace(line_start, first.span.start, " ".repeat(indentation));
+        }
+
+        max_indentation = None;
+        for token in &line {
+            let pos = line_index.line_col(token.span.start, Encoding::Char);
+            let column = if pos.line == start.line {
+                pos.column - start.column + indentation
+            } else {
+                pos.column
+            };
+
+            if is_opening(token.kind) {
+                stack.push((token, column));
+                max_indentation = None;
+            } else if is_closing(token.kind) {
+                if stack.last().map(|(open, _)| closing_kind(open.kind)) == Some(token.kind) {
+                    max_indentation = stack.pop().map(|(_, col)| col);
+                } else {
+                    errors.push(ParseError::UnexpectedClosingDelimiter {
+                        found: token.kind,
+                        span: token.span,
+                    });
+                }
+            } else {
+                max_indentation = None;
+            }
+        }
+    }
+
+    errors.extend(
+        stack
+            .iter()
+            .rev()
+            .map(|(open, _)| ParseError::UnclosedDelimiter {
+                open: open.kind,
+                span: open.span,
+            }),
+    );
+
+    if !errors.is_empty() {
+        return Err(Error::Parse(errors));
+    }
+
+    Ok(edits.apply(src))
+}
+
+/// Groups the non-trivia tokens by the line they start on. A token that starts on the same line a
+/// multiline string ends on belongs to the line the string starts on.
+fn code_lines<'a, 'src>(
+    tokens: &'a [Token<'src>],
+    line_index: &LineIndex,
+) -> Vec<Vec<&'a Token<'src>>> {
+    let mut lines: Vec<Vec<&Token>> = vec![];
+    let mut last_line = None;
+
+    for token in tokens.iter().filter(|t| !t.kind.is_trivia()) {
+        let start_line = line_index.line_col(token.span.start, Encoding::Utf8).line;
+
+        match lines.last_mut() {
+            Some(line) if last_line.is_some_and(|l| start_line <= l) => line.push(token),
+            _ => lines.push(vec![token]),
+        }
+
+        last_line = Some(line_index.line_col(token.span.end, Encoding::Utf8).line);
+    }
+
+    lines
+}
+
+fn is_opening(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::LParen | SyntaxKind::LBracket | SyntaxKind::LBrace
+    )
+}
+
+fn is_closing(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::RParen | SyntaxKind::RBracket | SyntaxKind::RBrace
+    )
+}
+
+fn closing_kind(open: SyntaxKind) -> SyntaxKind {
+    match open {
+        SyntaxKind::LParen => SyntaxKind::RParen,
+        SyntaxKind::LBracket => SyntaxKind::RBracket,
+        SyntaxKind::LBrace => SyntaxKind::RBrace,
+        _ => unreachable!("{:?} is not an opening delimiter.", open),
+    }
+}
+
+fn closing_text(open: SyntaxKind) -> &'static str {
+    match closing_kind(open) {
+        SyntaxKind::RParen => ")",
+        SyntaxKind::RBracket => "]",
+        _ => "}",
+    }
+}
+
+/// Non-overlapping replacements of byte ranges in a source text.
+#[derive(Default)]
+struct Edits {
+    edits: Vec<(usize, usize, String)>,
+}
+
+impl Edits {
+    fn replace(&mut self, start: usize, end: usize, text: String) {
+        self.edits.push((start, end, text));
+    }
+
+    fn insert(&mut self, offset: usize, text: String) {
+        if !text.is_empty() {
+            self.replace(offset, offset, text);
+        }
+    }
+
+    fn remove(&mut self, token: &Token) {
+        self.replace(token.span.start, token.span.end, String::new());
+    }
+
+    fn apply(mut self, src: &str) -> String {
+        // Insertions come before removals starting at the same offset
+        self.edits.sort_by_key(|(start, end, _)| (*start, *end));
+
+        let mut result = String::with_capacity(src.len());
+        let mut pos = 0;
+
+        for (start, end, text) in self.edits {
+            result.push_str(&src[pos..start]);
+            result.push_str(&text);
+            pos = end;
+        }
+
+        result.push_str(&src[pos..]);
+        result
+    }
+}
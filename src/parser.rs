@@ -1,10 +1,10 @@
 use std::{iter::Peekable, vec::IntoIter};
 
 use crate::{
-    error::Error,
+    error::{Error, ParseError},
     kind::SyntaxKind,
     lexer::lex,
-    node::{Span, SyntaxElement, Token},
+    node::{SyntaxElement, Token},
 };
 
 // TODO: Error recovery
@@ -17,13 +17,24 @@ pub fn parse<'src>(src: &'src str) -> Result<SyntaxElement<'src>, Error<'src>> {
     Ok(root)
 }
 
+/// Parses `src` like [`parse`], but returns the tree even if it contains unclosed or unexpected
+/// delimiters.
+pub fn parse_partial<'src>(src: &'src str) -> Result<SyntaxElement<'src>, Error<'src>> {
+    let mut p = Parser::new(src)?;
+
+    exprs(&mut p, &[SyntaxKind::End]);
+    let root = SyntaxElement::node(SyntaxKind::Root, p.nodes);
+
+    Ok(root)
+}
+
 struct Marker(usize);
 
 struct Parser<'src> {
     lexer: Peekable<IntoIter<Token<'src>>>,
     n_trivia: usize,
     nodes: Vec<SyntaxElement<'src>>,
-    errors: Vec<(String, Span)>,
+    errors: Vec<ParseError>,
 }
 
 impl<'src> Parser<'src> {
@@ -109,12 +120,14 @@ impl<'src> Parser<'src> {
         is_kind
     }
 
-    fn expect(&mut self, kind: SyntaxKind) {
+    /// Expects the closing delimiter of the container opened at `open`.
+    fn expect_closing(&mut self, open: &Marker, kind: SyntaxKind) {
         if !self.eat_if(kind) {
-            let m = self.marker();
-            let pos = self.nodes.get(m.0 - 1).map(|n| n.span().end).unwrap_or(0);
-            self.errors
-                .push((format!("expected {}", kind.name()), (pos..pos).into()))
+            let open = &self.nodes[open.0];
+            self.errors.push(ParseError::UnclosedDelimiter {
+                open: *open.kind(),
+                span: open.span(),
+            })
         }
     }
 
@@ -123,8 +136,10 @@ impl<'src> Parser<'src> {
         // TODO: Produce a SyntaxElement::Error?
         self.eat();
         let node = &self.nodes[m.0];
-        self.errors
-            .push((format!("unexpected {}", node.kind().name()), node.span()))
+        self.errors.push(ParseError::UnexpectedClosingDelimiter {
+            found: *node.kind(),
+            span: node.span(),
+        })
     }
 }
 
@@ -156,7 +171,7 @@ fn list(p: &mut Parser) {
     let m = p.marker();
     p.assert(SyntaxKind::LParen);
     exprs(p, &[SyntaxKind::RParen, SyntaxKind::End]);
-    p.expect(SyntaxKind::RParen);
+    p.expect_closing(&m, SyntaxKind::RParen);
     p.wrap(m, SyntaxKind::List);
 }
 
@@ -164,7 +179,7 @@ fn sequence(p: &mut Parser) {
     let m = p.marker();
     p.assert(SyntaxKind::LBracket);
     exprs(p, &[SyntaxKind::RBracket, SyntaxKind::End]);
-    p.expect(SyntaxKind::RBracket);
+    p.expect_closing(&m, SyntaxKind::RBracket);
     p.wrap(m, SyntaxKind::Sequence);
 }
 
@@ -172,18 +187,30 @@ fn table(p: &mut Parser) {
     let m = p.marker();
     p.assert(SyntaxKind::LBrace);
 
-    // TODO: Improve error message when table contains an odd number of exprs and/or unexpected tokens
     while !p.at_one_of(&[SyntaxKind::RBrace, SyntaxKind::End]) {
         pair(p)
     }
-    p.expect(SyntaxKind::RBrace);
+    p.expect_closing(&m, SyntaxKind::RBrace);
     p.wrap(m, SyntaxKind::Table);
 }
 
 fn pair(p: &mut Parser) {
     let m = p.marker();
     expr(p);
-    expr(p);
+
+    if p.at_one_of(&[SyntaxKind::RBrace, SyntaxKind::End]) {
+        let key = &p.nodes[m.0];
+        if !matches!(
+            key.kind(),
+            SyntaxKind::RParen | SyntaxKind::RBracket | SyntaxKind::RBrace
+        ) {
+            p.errors
+                .push(ParseError::MissingTableValue { span: key.span() });
+        }
+    } else {
+        expr(p);
+    }
+
     p.wrap(m, SyntaxKind::Pair);
 }
 
@@ -0,0 +1,322 @@
+use pretty::{Render, RenderAnnotated};
+
+use crate::{
+    config::{Config, IndentStyle},
+    doc::{Annotation, ArenaDoc},
+};
+
+/// A rendered line, along with where its comment starts.
+#[derive(Default)]
+struct Line {
+    text: String,
+    comment: Option<Comment>,
+    /// Whether the line ends inside verbatim text, so its trailing whitespace must be kept.
+    verbatim_end: bool,
+    /// Whether the line starts with indentation kept as written, which must not be replaced.
+    kept_indent: bool,
+}
+
+#[derive(Copy, Clone)]
+struct Comment {
+    /// The byte offset of the comment in the line.
+    start: usize,
+    /// Whether the comment follows code on the same line.
+    trailing: bool,
+    /// Whether the comment is kept where it is, because aligning or reflowing it is disabled for
+    /// the expression it is in.
+    fixed: bool,
+}
+
+/// Collects rendered output as lines, remembering where annotated parts of the document end up.
+struct Writer {
+    lines: Vec<Line>,
+    annotations: Vec<Annotation>,
+}
+
+impl Writer {
+    fn current(&mut self) -> &mut Line {
+        self.lines.last_mut().expect("Writer should have a line.")
+    }
+}
+
+impl Render for Writer {
+    type Error = ();
+
+    fn write_str(&mut self, s: &str) -> Result<usize, Self::Error> {
+        let annotation = self.annotations.last().copied();
+
+        for (i, part) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.current().verbatim_end =
+                    matches!(annotation, Some(Annotation::Verbatim | Annotation::Kept));
+                self.lines.push(Line {
+                    kept_indent: self.annotations.contains(&Annotation::Ignored),
+                    ..Default::default()
+                });
+            }
+
+            let line = self.current();
+            if line.comment.is_none() {
+                line.comment = match annotation {
+                    Some(Annotation::TrailingComment { align }) => Some(Comment {
+                        start: line.text.len(),
+                        trailing: true,
+                        fixed: !align,
+                    }),
+                    Some(Annotation::LineComment { reflow }) => Some(Comment {
+                        start: line.text.len(),
+                        trailing: false,
+                        fixed: !reflow,
+                    }),
+                    Some(Annotation::Verbatim | Annotation::Kept | Annotation::Ignored) | None => {
+                        None
+                    }
+                };
+            }
+            line.text.push_str(part);
+        }
+
+        Ok(s.len())
+    }
+
+    fn fail_doc(&self) -> Self::Error {}
+}
+
+impl<'a> RenderAnnotated<'a, Annotation> for Writer {
+    fn push_annotation(&mut self, annotation: &'a Annotation) -> Result<(), Self::Error> {
+        // Kept text brings the indentation of its first line along
+        let line = self.current();
+        if *annotation == Annotation::Kept && line.text.trim().is_empty() {
+            line.text.clear();
+            line.kept_indent = true;
+        }
+
+        self.annotations.push(*annotation);
+        Ok(())
+    }
+
+    fn pop_annotation(&mut self) -> Result<(), Self::Error> {
+        self.annotations.pop();
+        Ok(())
+    }
+}
+
+/// Renders a document into text with lines ending in `line_ending`.
+///
+/// Line breaks inside verbatim text are kept as they are.
+pub fn render(doc: ArenaDoc, config: &Config, line_ending: &str) -> String {
+    let mut writer = Writer {
+        lines: vec![Line::default()],
+        annotations: vec![],
+    };
+    doc.render_raw(config.max_width, &mut writer)
+        .expect("Rendering into a string should not fail.");
+
+    let mut lines = writer.lines;
+
+    for line in lines.iter_mut().filter(|line| !line.verbatim_end) {
+        let len = line.text.trim_end().len();
+        line.text.truncate(len);
+    }
+
+    if config.reflow_comments {
+        lines = reflow_comments(lines, config);
+    }
+
+    if config.align_comments {
+        align_comments(&mut lines, config);
+    }
+
+    if config.indent_style == IndentStyle::Tabs {
+        indent_with_tabs(&mut lines, config);
+    }
+
+    let mut text = String::new();
+    let last = lines.len() - 1;
+    for (i, line) in lines.into_iter().enumerate() {
+        text.push_str(&line.text);
+        if i < last {
+            text.push_str(if line.verbatim_end { "\n" } else { line_ending });
+        }
+    }
+
+    text
+}
+
+/// Replaces the leading spaces of every line that does not start inside verbatim text or with
+/// indentation kept as written with tabs.
+fn indent_with_tabs(lines: &mut [Line], config: &Config) {
+    let mut starts_in_verbatim = false;
+
+    for line in lines {
+        if !starts_in_verbatim && !line.kept_indent {
+            let text = line.text.trim_start_matches(' ');
+            let column = line.text.len() - text.len();
+            line.text = format!("{}{}", config.indentation(column), text);
+        }
+        starts_in_verbatim = line.verbatim_end;
+    }
+}
+
+/// Moves the trailing comments of consecutive lines into a common column.
+///
+/// A run of lines is left as is if aligning it would pad a comment by more than the maximum
+/// padding or push it past the maximum width.
+fn align_comments(lines: &mut [Line], config: &Config) {
+    let mut start = 0;
+
+    while start < lines.len() {
+        let end = lines[start..]
+            .iter()
+            .position(|line| !line.comment.is_some_and(|c| c.trailing && !c.fixed))
+            .map(|i| start + i)
+            .unwrap_or(lines.len());
+
+        if start == end {
+            start += 1;
+            continue;
+        }
+
+        let run = &mut lines[start..end];
+        start = end;
+
+        // The code and comment of every line, split at the comment
+        let parts = run
+            .iter()
+            .map(|line| {
+                let start = line.comment.map(|c| c.start).unwrap_or(0);
+                let (code, comment) = line.text.split_at(start);
+                (code.trim_end().to_string(), comment.to_string())
+            })
+            .collect::<Vec<_>>();
+
+        let column = parts
+            .iter()
+            .map(|(code, _)| config.width(code) + 1)
+            .max()
+            .unwrap_or(0)
+            .max(config.comment_min_column);
+
+        let fits = parts.iter().all(|(code, comment)| {
+            let width = config.width(code);
+            column - width - 1 <= config.comment_max_padding
+                && column + config.width(comment) <= config.max_width
+        });
+        if !fits {
+            continue;
+        }
+
+        for (line, (code, comment)) in run.iter_mut().zip(parts) {
+            let padding = column - config.width(&code);
+            line.comment = Some(Comment {
+                start: code.len() + padding,
+                trailing: true,
+                fixed: false,
+            });
+            line.text = format!("{}{}{}", code, " ".repeat(padding), comment);
+        }
+    }
+}
+
+/// Reflows runs of comments on their own lines with the same indentation and semicolons to fill
+/// the maximum width.
+///
+/// Blank comment lines, list items, indented code examples, URLs and lispfmt directives are kept
+/// on their own lines as written.
+fn reflow_comments(lines: Vec<Line>, config: &Config) -> Vec<Line> {
+    let mut result: Vec<Line> = vec![];
+    // The indentation and semicolons of the paragraph being filled, along with its words
+    let mut paragraph: Option<(String, Vec<String>)> = None;
+
+    for line in lines {
+        let prefix = line
+            .comment
+            .filter(|c| !c.trailing && !c.fixed)
+            .map(|c| comment_prefix(&line.text, c.start));
+
+        let Some(prefix) = prefix else {
+            flush(&mut result, &mut paragraph, config);
+            result.push(line);
+            continue;
+        };
+
+        let content = &line.text[prefix.len()..];
+        let content = content.strip_prefix(' ').unwrap_or(content);
+
+        if !is_reflowable(content) {
+            flush(&mut result, &mut paragraph, config);
+            result.push(line);
+            continue;
+        }
+
+        if paragraph.as_ref().is_some_and(|(p, _)| *p != prefix) {
+            flush(&mut result, &mut paragraph, config);
+        }
+
+        let words = content.split_whitespace().map(str::to_string);
+        paragraph
+            .get_or_insert_with(|| (prefix.to_string(), vec![]))
+            .1
+            .extend(words);
+    }
+
+    flush(&mut result, &mut paragraph, config);
+    result
+}
+
+/// Returns the indentation and semicolons a comment line starts with.
+fn comment_prefix(text: &str, start: usize) -> String {
+    let semicolons = text[start..].len() - text[start..].trim_start_matches(';').len();
+    text[..start + semicolons].to_string()
+}
+
+pub(crate) fn is_reflowable(content: &str) -> bool {
+    let is_list_item = {
+        let marker = content.trim_start_matches(|c: char| c.is_ascii_digit());
+        let numbered = marker.len() < content.len() && marker.starts_with(['.', ')']);
+        content.starts_with(['-', '*', '+']) || numbered
+    };
+
+    !(content.trim().is_empty()
+        || content.starts_with(char::is_whitespace)
+        || is_list_item
+        || content.contains("://")
+        || content.starts_with("###")
+        || content.starts_with("lispfmt-"))
+}
+
+/// Fills the lines of a paragraph greedily and appends them to `lines`.
+fn flush(lines: &mut Vec<Line>, paragraph: &mut Option<(String, Vec<String>)>, config: &Config) {
+    let Some((prefix, words)) = paragraph.take() else {
+        return;
+    };
+
+    let start = prefix.trim_end_matches(';').len();
+    let mut text = String::new();
+
+    for word in words {
+        if !text.is_empty() && config.width(&text) + 1 + config.width(&word) > config.max_width {
+            lines.push(comment_line(std::mem::take(&mut text), start));
+        }
+
+        if text.is_empty() {
+            text.push_str(&prefix);
+        }
+        text.push(' ');
+        text.push_str(&word);
+    }
+
+    lines.push(comment_line(text, start));
+}
+
+fn comment_line(text: String, start: usize) -> Line {
+    Line {
+        text,
+        comment: Some(Comment {
+            start,
+            trailing: false,
+            fixed: false,
+        }),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,98 @@
+//! Decoding of input that is not guaranteed to be valid UTF-8.
+
+use crate::{diagnostic::Diagnostic, error_code::ErrorCode};
+
+/// An input that is not valid UTF-8.
+#[derive(Debug)]
+pub struct InvalidUtf8 {
+    /// The input with every invalid sequence replaced by `U+FFFD`, to show the error in.
+    pub text: String,
+    /// The byte offset of the first invalid sequence.
+    pub offset: usize,
+}
+
+impl InvalidUtf8 {
+    pub fn diagnostic(&self) -> Diagnostic {
+        let end = self.offset + char::REPLACEMENT_CHARACTER.len_utf8();
+        Diagnostic::error(
+            ErrorCode::InvalidUtf8,
+            format!("invalid UTF-8 at byte {}", self.offset),
+            (self.offset..end).into(),
+        )
+    }
+}
+
+/// Decodes `bytes` as UTF-8.
+pub fn decode(bytes: Vec<u8>) -> Result<String, InvalidUtf8> {
+    String::from_utf8(bytes).map_err(|e| InvalidUtf8 {
+        offset: e.utf8_error().valid_up_to(),
+        text: String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    })
+}
+
+/// Text decoded from an input, along with the invalid sequences of the input that were replaced
+/// by `U+FFFD` in it.
+#[derive(Debug)]
+pub struct Decoded {
+    pub text: String,
+    /// The invalid sequence each `U+FFFD` of `text` stands for, in order, or `None` for those
+    /// that were already in the input.
+    replaced: Vec<Option<Vec<u8>>>,
+}
+
+impl From<String> for Decoded {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            replaced: vec![],
+        }
+    }
+}
+
+impl Decoded {
+    /// Encodes text formatted from [`Decoded::text`], restoring the invalid sequences of the
+    /// input.
+    ///
+    /// Formatting keeps the `U+FFFD` characters of the text in order, so the `n`th one in
+    /// `formatted` is the `n`th one in the decoded text.
+    pub fn encode(&self, formatted: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(formatted.len());
+        let mut replaced = self.replaced.iter();
+
+        for c in formatted.chars() {
+            match c {
+                char::REPLACEMENT_CHARACTER => match replaced.next() {
+                    Some(Some(sequence)) => bytes.extend_from_slice(sequence),
+                    Some(None) | None => bytes.extend_from_slice("\u{fffd}".as_bytes()),
+                },
+                c => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+
+        bytes
+    }
+}
+
+/// Decodes `bytes` as UTF-8, replacing every invalid sequence with `U+FFFD` and keeping track of
+/// it, so that [`Decoded::encode`] can restore it.
+pub fn decode_lossy(bytes: &[u8]) -> Decoded {
+    let mut text = String::with_capacity(bytes.len());
+    let mut replaced = vec![];
+
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        replaced.extend(
+            chunk
+                .valid()
+                .matches(char::REPLACEMENT_CHARACTER)
+                .map(|_| None),
+        );
+
+        if !chunk.invalid().is_empty() {
+            text.push(char::REPLACEMENT_CHARACTER);
+            replaced.push(Some(chunk.invalid().to_vec()));
+        }
+    }
+
+    Decoded { text, replaced }
+}
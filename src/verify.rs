@@ -0,0 +1,196 @@
+use similar::TextDiff;
+
+use crate::{
+    config::Config,
+    diagnostic::{Diagnostic, Related},
+    doc,
+    error_code::ErrorCode,
+    format::format_text,
+    kind::SyntaxKind,
+    node::{Span, SyntaxElement},
+    parser::parse,
+};
+
+/// A way in which the formatted output of a source text is wrong.
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The source could not be parsed, so there is nothing to compare the output with.
+    InvalidSource(Diagnostic),
+    /// The formatted output could not be parsed.
+    Unparseable,
+    /// Formatting the formatted output changed it again.
+    NotIdempotent {
+        /// A unified diff from the formatted output to the output of formatting it again.
+        diff: String,
+    },
+    /// The formatted output does not contain the same code as the source.
+    TokensChanged {
+        /// The first differing token in the source.
+        original: Span,
+        /// The first differing token in the formatted output.
+        formatted: Span,
+    },
+}
+
+impl VerifyError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            VerifyError::InvalidSource(diagnostic) => diagnostic.code,
+            VerifyError::NotIdempotent { .. } => ErrorCode::NotIdempotent,
+            VerifyError::Unparseable | VerifyError::TokensChanged { .. } => {
+                ErrorCode::ChangedTokens
+            }
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            VerifyError::InvalidSource(diagnostic) => diagnostic.message.clone(),
+            VerifyError::Unparseable => {
+                "internal error: the formatted output cannot be parsed".to_string()
+            }
+            VerifyError::NotIdempotent { .. } => {
+                "internal error: formatting the formatted output changes it again".to_string()
+            }
+            VerifyError::TokensChanged { .. } => {
+                "internal error: formatting changed the code".to_string()
+            }
+        }
+    }
+
+    /// The span in the source the error is reported at.
+    pub fn span(&self) -> Span {
+        match self {
+            VerifyError::InvalidSource(diagnostic) => diagnostic.span,
+            VerifyError::TokensChanged { original, .. } => *original,
+            VerifyError::Unparseable | VerifyError::NotIdempotent { .. } => (0..0).into(),
+        }
+    }
+
+    /// Returns the diagnostic reporting the error in the source, with the differing token of
+    /// `formatted` or the diff of the reformatted output attached to it.
+    pub fn diagnostic(&self, formatted: &str) -> Diagnostic {
+        let diagnostic = match self {
+            VerifyError::InvalidSource(diagnostic) => return diagnostic.clone(),
+            _ => Diagnostic::error(self.code(), self.message(), self.span()),
+        };
+
+        match self {
+            VerifyError::NotIdempotent { diff } => diagnostic.with_note(diff.clone()),
+            VerifyError::TokensChanged {
+                formatted: span, ..
+            } => diagnostic.with_related(Related {
+                name: "formatted".to_string(),
+                text: formatted.to_string(),
+                span: *span,
+                message: "the formatted output differs here".to_string(),
+            }),
+            _ => diagnostic,
+        }
+    }
+}
+
+/// Checks that `formatted`, the formatted output of `src`, contains the same code as `src` and
+/// does not change when it is formatted again.
+pub fn verify(src: &str, formatted: &str, config: &Config) -> Result<(), VerifyError> {
+    check_equivalence(src, formatted, config)?;
+
+    let reformatted = format_text(formatted, config).map_err(|_| VerifyError::Unparseable)?;
+    if reformatted != formatted {
+        let diff = TextDiff::from_lines(formatted, &reformatted)
+            .unified_diff()
+            .header("formatted", "reformatted")
+            .to_string();
+        return Err(VerifyError::NotIdempotent { diff });
+    }
+
+    Ok(())
+}
+
+/// Checks that `formatted`, the formatted output of `src`, contains the same tokens and comments
+/// as `src`.
+///
+/// Tokens are compared byte for byte, except that comments and docstrings formatted with
+/// `config` are compared as sequences of whitespace-separated words, so changes to their
+/// whitespace and to the semicolons of comments are allowed.
+pub fn check_equivalence(src: &str, formatted: &str, config: &Config) -> Result<(), VerifyError> {
+    let tree = parse(src).map_err(|error| {
+        let diagnostic = error.diagnostics().into_iter().next();
+        VerifyError::InvalidSource(diagnostic.expect("Error should have a diagnostic."))
+    })?;
+    let formatted_tree = parse(formatted).map_err(|_| VerifyError::Unparseable)?;
+
+    let (mut original, mut output) = (vec![], vec![]);
+    words(&tree, config, &mut original);
+    words(&formatted_tree, config, &mut output);
+
+    let mismatch = original
+        .iter()
+        .zip(&output)
+        .find(|((a, _), (b, _))| a != b)
+        .map(|((_, a), (_, b))| (*a, *b))
+        .or_else(|| match original.len().cmp(&output.len()) {
+            std::cmp::Ordering::Equal => None,
+            _ => Some((
+                original
+                    .get(output.len())
+                    .map(|(_, s)| *s)
+                    .unwrap_or(end(src)),
+                output
+                    .get(original.len())
+                    .map(|(_, s)| *s)
+                    .unwrap_or(end(formatted)),
+            )),
+        });
+
+    match mismatch {
+        Some((original, span)) => Err(VerifyError::TokensChanged {
+            original,
+            formatted: span,
+        }),
+        None => Ok(()),
+    }
+}
+
+fn end(src: &str) -> Span {
+    (src.len()..src.len()).into()
+}
+
+/// A unit of code compared between the source and the formatted output.
+#[derive(Debug, PartialEq)]
+enum Word<'a> {
+    /// A token, which must be kept byte for byte.
+    Token(&'a str),
+    /// A word of a comment or docstring, which can be moved to another line.
+    Text(&'a str),
+}
+
+/// Collects the words of the non-whitespace tokens of a tree, along with the span of the token
+/// each word belongs to.
+fn words<'a>(elem: &'a SyntaxElement<'a>, config: &Config, words: &mut Vec<(Word<'a>, Span)>) {
+    let docstring = doc::docstring(elem, config);
+
+    for child in elem.children() {
+        let span = child.span();
+        match child.kind() {
+            SyntaxKind::Space | SyntaxKind::Newline | SyntaxKind::End => (),
+            SyntaxKind::Comment => words.extend(
+                child
+                    .text()
+                    .trim_start_matches(';')
+                    .split_whitespace()
+                    .map(|word| (Word::Text(word), span)),
+            ),
+            SyntaxKind::String if docstring.is_some_and(|d| std::ptr::eq(d, child)) => words
+                .extend(
+                    child
+                        .text()
+                        .split_whitespace()
+                        .map(|word| (Word::Text(word), span)),
+                ),
+            SyntaxKind::HashDirective => words.push((Word::Token(child.text().trim_end()), span)),
+            _ if matches!(child, SyntaxElement::Node(_)) => self::words(child, config, words),
+            _ => words.push((Word::Token(child.text()), span)),
+        }
+    }
+}
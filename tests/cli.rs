@@ -0,0 +1,192 @@
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+use serde_json::{Value, json};
+
+/// Creates a directory with the given files.
+fn project(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lispfmt-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (file, contents) in files {
+        fs::write(dir.join(file), contents).unwrap();
+    }
+    dir
+}
+
+/// Runs lispfmt in `dir`.
+fn lispfmt(dir: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lispfmt"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// A file with two unformatted expressions, a file with parse errors after non-ASCII characters
+/// and a formatted file.
+const FILES: &[(&str, &[u8])] = &[
+    ("a.clj", b"(foo\nbar)\n(baz\nqux)\n"),
+    ("b.clj", "(\u{e9} \"\u{1f600}\" (x\n  (y)\n".as_bytes()),
+    ("c.clj", b"(ok)\n"),
+];
+
+#[test]
+fn unreadable_files_are_reported_and_the_others_formatted() {
+    let dir = project("cli", &[("a.clj", b"(foo\nbar)\n")]);
+    let output = lispfmt(&dir, &["--error-format", "short", "missing.clj", "a.clj"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("error[LF008]: unable to read"),
+        "{}",
+        stderr
+    );
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "(foo\n  bar)\n");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn check_reports_json_lines() {
+    let dir = project("json", FILES);
+    let output = lispfmt(
+        &dir,
+        &[
+            "--check",
+            "--error-format",
+            "json",
+            "a.clj",
+            "b.clj",
+            "c.clj",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    let diagnostics = String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect::<Vec<Value>>();
+    assert_eq!(diagnostics.len(), 3);
+
+    // Only the first difference of a file is reported
+    assert_eq!(
+        diagnostics[0],
+        json!({
+            "file": "a.clj",
+            "code": "LF100",
+            "severity": "warning",
+            "message": "file would be reformatted",
+            "span": { "start": 5, "end": 6 },
+            "start": { "line": 2, "column": 1 },
+            "end": { "line": 2, "column": 2 },
+            "related": [],
+            "note": null,
+        })
+    );
+
+    // Columns are counted in chars
+    assert_eq!(diagnostics[1]["file"], "b.clj");
+    assert_eq!(diagnostics[1]["code"], "LF001");
+    assert_eq!(diagnostics[1]["severity"], "error");
+    assert_eq!(diagnostics[1]["span"], json!({ "start": 11, "end": 12 }));
+    assert_eq!(diagnostics[1]["start"], json!({ "line": 1, "column": 8 }));
+    assert_eq!(diagnostics[2]["span"], json!({ "start": 0, "end": 1 }));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn check_reports_a_sarif_log() {
+    let dir = project("sarif", FILES);
+    let output = lispfmt(
+        &dir,
+        &[
+            "--check",
+            "--error-format",
+            "sarif",
+            "a.clj",
+            "b.clj",
+            "c.clj",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    let log: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+
+    let run = &log["runs"][0];
+    let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+    let rule_ids = rules.iter().map(|rule| &rule["id"]).collect::<Vec<_>>();
+    assert_eq!(rule_ids, ["LF100", "LF001"]);
+    assert_eq!(
+        rules[0]["shortDescription"]["text"],
+        "file would be reformatted"
+    );
+
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["ruleId"], "LF100");
+    assert_eq!(results[0]["level"], "warning");
+    assert_eq!(
+        results[0]["locations"][0]["physicalLocation"],
+        json!({
+            "artifactLocation": { "uri": "a.clj" },
+            "region": {
+                "startLine": 2,
+                "startColumn": 1,
+                "endLine": 2,
+                "endColumn": 2,
+                "byteOffset": 5,
+                "byteLength": 1,
+            },
+        })
+    );
+
+    // Columns are counted in UTF-16 code units
+    assert_eq!(results[1]["ruleId"], "LF001");
+    assert_eq!(results[1]["level"], "error");
+    assert_eq!(
+        results[1]["locations"][0]["physicalLocation"],
+        json!({
+            "artifactLocation": { "uri": "b.clj" },
+            "region": {
+                "startLine": 1,
+                "startColumn": 9,
+                "endLine": 1,
+                "endColumn": 10,
+                "byteOffset": 11,
+                "byteLength": 1,
+            },
+        })
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn indentation_inside_a_string_is_reported_as_a_diagnostic() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lispfmt"))
+        .args(["indent", "7", "--error-format", "json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"(foo \"ab\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let diagnostic: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(diagnostic["code"], "LF004");
+    assert_eq!(diagnostic["file"], "<stdin>");
+    assert_eq!(diagnostic["span"], json!({ "start": 7, "end": 7 }));
+}
@@ -0,0 +1,137 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use lispfmt::editorconfig;
+
+/// Creates a fresh directory for a test containing `files`.
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lispfmt-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for (path, text) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+    dir
+}
+
+fn lispfmt(file: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lispfmt"))
+        .args(args)
+        .arg(file)
+        .output()
+        .unwrap()
+}
+
+fn formatted(file: &Path, args: &[&str]) -> String {
+    let output = lispfmt(file, args);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+const EDITORCONFIG: &str = "\
+root = true
+
+[*]
+indent_size = 4
+
+[*.{clj,cljs}]
+indent_size = tab
+tab_width = 3
+
+[lib/*.el]
+indent_size = 1
+";
+
+#[test]
+fn editorconfig_sections_are_matched_by_glob() {
+    let dir = project(
+        "editorconfig",
+        &[
+            (".editorconfig", EDITORCONFIG),
+            ("a.el", ""),
+            ("a.cljs", ""),
+            ("lib/b.el", ""),
+            ("lib/nested/c.el", ""),
+        ],
+    );
+    let list_indent = |path: &str| editorconfig::options(&dir.join(path)).list_indent;
+
+    assert_eq!(list_indent("a.el"), Some(4));
+    assert_eq!(list_indent("a.cljs"), Some(3));
+    assert_eq!(list_indent("lib/b.el"), Some(1));
+    assert_eq!(list_indent("lib/nested/c.el"), Some(4));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn command_line_takes_precedence_over_lispfmt_toml_over_editorconfig() {
+    let dir = project(
+        "precedence",
+        &[
+            (".editorconfig", EDITORCONFIG),
+            ("a.clj", "(foo\nbar)\n"),
+            ("toml/lispfmt.toml", "list_indent = 6\nalign = true\n"),
+            ("toml/b.clj", "(foo\nbar)\n{:a 1 ; one\n:bbb 2}\n"),
+        ],
+    );
+
+    assert_eq!(formatted(&dir.join("a.clj"), &[]), "(foo\n   bar)\n");
+    assert_eq!(
+        formatted(&dir.join("toml/b.clj"), &[]),
+        "(foo\n      bar)\n{:a   1 ; one\n :bbb 2}\n"
+    );
+    assert_eq!(
+        formatted(
+            &dir.join("toml/b.clj"),
+            &["--list-indent", "5", "--no-align"]
+        ),
+        "(foo\n     bar)\n{:a 1 ; one\n :bbb 2}\n"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn wrap_docstrings_requires_docstrings_after_merging() {
+    let dir = project(
+        "docstrings",
+        &[
+            ("a.clj", "(defn f\n  \"Docs.\"\n  [])\n"),
+            ("toml/lispfmt.toml", "docstrings = true\n"),
+            ("toml/b.clj", "(defn f\n  \"Docs.\"\n  [])\n"),
+        ],
+    );
+
+    let output = lispfmt(&dir.join("a.clj"), &["--wrap-docstrings"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("LF006"));
+
+    formatted(&dir.join("toml/b.clj"), &["--wrap-docstrings"]);
+    let output = lispfmt(
+        &dir.join("toml/b.clj"),
+        &["--wrap-docstrings", "--no-docstrings"],
+    );
+    assert!(!output.status.success());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ambiguous_width_must_be_1_or_2() {
+    let dir = project(
+        "ambiguous-width",
+        &[
+            ("lispfmt.toml", "ambiguous_width = 7\n"),
+            ("a.clj", "(a)\n"),
+        ],
+    );
+
+    let output = lispfmt(&dir.join("a.clj"), &[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("LF006"));
+    assert!(stderr.contains("`ambiguous_width` must be 1 or 2, not 7"));
+    fs::remove_dir_all(dir).unwrap();
+}
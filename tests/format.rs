@@ -0,0 +1,582 @@
+use lispfmt::{
+    config::{Config, EndOfLine, IndentStyle},
+    directive::warnings,
+    error_code::ErrorCode,
+    format::format_text,
+    parser::parse,
+};
+
+fn format(src: &str) -> String {
+    format_with(src, &Config::default())
+}
+
+fn format_with(src: &str, config: &Config) -> String {
+    format_text(src, config).expect("Input should be valid.")
+}
+
+fn docstrings() -> Config {
+    Config {
+        docstrings: true,
+        wrap_docstrings: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn trailing_whitespace_is_removed() {
+    assert_eq!(format("(foo   \n  bar)   \n"), "(foo\n  bar)\n");
+}
+
+#[test]
+fn multiline_string_is_kept_byte_for_byte() {
+    let src = "(def x \"first   \n  second\t\n\n   \n\")\n";
+    assert_eq!(format(src), src);
+}
+
+#[test]
+fn multiline_string_in_reindented_form_is_kept_byte_for_byte() {
+    let string = "\"a  \n\t b \r\n  \"";
+    let src = format!("(foo\n      {}   \n   bar)\n", string);
+    let formatted = format(&src);
+
+    assert!(formatted.contains(string));
+    assert_eq!(formatted, format!("(foo\n  {}\n  bar)\n", string));
+}
+
+#[test]
+fn whitespace_after_multiline_string_is_removed() {
+    assert_eq!(format("(foo \"a \nb\"   )\n"), "(foo \"a \nb\")\n");
+}
+
+#[test]
+fn docstring_after_name_is_wrapped() {
+    let src = "(defn f\n      \"Docs that were\n      pasted here.\"\n  [x]\n  x)\n";
+    assert_eq!(
+        format_with(src, &docstrings()),
+        "(defn f\n  \"Docs that were pasted here.\"\n  [x]\n  x)\n"
+    );
+}
+
+#[test]
+fn docstring_after_argument_list_is_wrapped() {
+    let src = "(defun f (x)\n      \"Docs that were\n      pasted here.\"\n  x)\n";
+    assert_eq!(
+        format_with(src, &docstrings()),
+        "(defun f (x)\n  \"Docs that were pasted here.\"\n  x)\n"
+    );
+}
+
+#[test]
+fn body_string_after_argument_vector_is_not_a_docstring() {
+    let src = "(defn f [x]\n  \"a literal   value\"\n  x)\n";
+    assert_eq!(format_with(src, &docstrings()), src);
+}
+
+#[test]
+fn body_string_after_docstring_is_kept() {
+    let src = "(defn f\n  \"Docs.\"\n  [x]\n  \"a   body\n    string\"\n  x)\n";
+    assert_eq!(format_with(src, &docstrings()), src);
+
+    let src = "(defun f (x)\n  \"Docs.\"\n  \"a   body\n    string\"\n  x)\n";
+    assert_eq!(format_with(src, &docstrings()), src);
+}
+
+#[test]
+fn string_value_of_definition_is_kept() {
+    let src = "(def x \"a   value\")\n(defun f () \"a   value\")\n";
+    assert_eq!(format_with(src, &docstrings()), src);
+}
+
+#[test]
+fn docstring_with_tabs_is_wrapped() {
+    let src = "(defn f\n  \"Doc\n\t\t\n   more\"\n  [] 1)\n";
+    assert_eq!(
+        format_with(src, &docstrings()),
+        "(defn f\n  \"Doc\n\t\t\n   more\"\n  [] 1)\n"
+    );
+
+    let src = "(defn f\n  \"Doc\n\tindented\n\twith tabs\"\n  [] 1)\n";
+    assert_eq!(
+        format_with(src, &docstrings()),
+        "(defn f\n  \"Doc indented with tabs\"\n  [] 1)\n"
+    );
+}
+
+#[test]
+fn ignored_comment_rules_keep_comments_of_the_next_expression() {
+    let config = Config {
+        align_comments: true,
+        normalize_comments: true,
+        reflow_comments: true,
+        ..Default::default()
+    };
+    let src = "\
+(do
+  ;; lispfmt-ignore align_comments normalize_comments reflow_comments -- hand written
+  (foo x ; x
+    yy ;;; y
+    ;; short
+    ;; lines
+    z)
+  (bar x ; x
+    yy ;;; y
+    ;; short
+    ;; lines
+    z))
+";
+    let formatted = "\
+(do
+  ;; lispfmt-ignore align_comments normalize_comments reflow_comments -- hand written
+  (foo x ; x
+    yy ;;; y
+    ;; short
+    ;; lines
+    z)
+  (bar x ; x
+    yy   ; y
+    ;; short lines
+    z))
+";
+    assert_eq!(format_with(src, &config), formatted);
+}
+
+#[test]
+fn ignored_docstrings_rule_keeps_docstring() {
+    let src = ";; lispfmt-ignore docstrings\n(defn f\n  \"Docs\n     here.\"\n  [])\n";
+    assert_eq!(format_with(src, &docstrings()), src);
+}
+
+#[test]
+fn ignore_comment_with_reason_keeps_expression() {
+    let src = ";; lispfmt-ignore -- generated\n(foo   1\n        2)\n";
+    assert_eq!(format(src), src);
+}
+
+#[test]
+fn top_level_headings_keep_their_semicolons() {
+    let config = Config {
+        normalize_comments: true,
+        ..Default::default()
+    };
+    let src = ";;;;Section\n;;;;; Subsection\n; text\n(foo)\n";
+    let formatted = ";;;; Section\n;;;;; Subsection\n;;; text\n(foo)\n";
+    assert_eq!(format_with(src, &config), formatted);
+}
+
+#[test]
+fn disabled_region_is_kept_byte_for_byte() {
+    let src = "\
+(foo
+      ; lispfmt-off
+      {:a 1   :b 2   
+       :c \"x
+  y\"}
+      ; lispfmt-on
+      bar)
+";
+    let formatted = "\
+(foo
+      ; lispfmt-off
+      {:a 1   :b 2   
+       :c \"x
+  y\"}
+      ; lispfmt-on
+  bar)
+";
+    assert_eq!(format(src), formatted);
+}
+
+#[test]
+fn disabled_region_keeps_tabs() {
+    let src = "; lispfmt-off\n(def m [1\t0\n\t0 1])\n; lispfmt-on\n(foo   bar)\n";
+    let formatted = "; lispfmt-off\n(def m [1\t0\n\t0 1])\n; lispfmt-on\n(foo bar)\n";
+    assert_eq!(format(src), formatted);
+}
+
+#[test]
+fn blank_lines_are_required_around_definitions_only() {
+    let config = Config {
+        blank_lines_between_definitions: 1,
+        ..Default::default()
+    };
+    let src = "(ns app)\n(defn f [])\n(define-key map \"a\" f)\n(default-settings)\n(defvar x)\n";
+    let formatted =
+        "(ns app)\n\n(defn f [])\n\n(define-key map \"a\" f)\n(default-settings)\n\n(defvar x)\n";
+    assert_eq!(format_with(src, &config), formatted);
+}
+
+fn aligned() -> Config {
+    Config {
+        align: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn let_bindings_are_aligned() {
+    let src = "(let [a 1\n      bbb (foo)\n      {:keys [c]} m]\n  a)\n";
+    let formatted = "(let [a           1\n      bbb         (foo)\n      {:keys [c]} m]\n  a)\n";
+    assert_eq!(format_with(src, &aligned()), formatted);
+}
+
+#[test]
+fn clauses_are_aligned_after_their_leading_expressions() {
+    let src = "(cond\n  (= a 1) :one\n  :else nil)\n(case x\n  1 :one\n  222 :many)\n";
+    let formatted = "(cond\n  (= a 1) :one\n  :else   nil)\n(case x\n  1   :one\n  222 :many)\n";
+    assert_eq!(format_with(src, &aligned()), formatted);
+}
+
+#[test]
+fn table_values_are_aligned() {
+    let src = "{:a 1\n :bbbb 2 ; c\n :cc 3}\n";
+    let formatted = "{:a    1\n :bbbb 2 ; c\n :cc   3}\n";
+    assert_eq!(format_with(src, &aligned()), formatted);
+}
+
+#[test]
+fn groups_on_one_line_are_not_aligned() {
+    let src = "(let [a 1 bb 2] a)\n";
+    assert_eq!(format_with(src, &aligned()), src);
+}
+
+#[test]
+fn groups_with_a_key_wider_than_the_maximum_are_not_aligned() {
+    let src = "(let [a 1\n      this-is-a-very-long-binding-name 2]\n  a)\n";
+    assert_eq!(format_with(src, &aligned()), src);
+}
+
+#[test]
+fn trailing_ignore_comment_disables_rules_for_the_expression_it_follows() {
+    let src = "(let [a 1\n      bbb 2] ; lispfmt-ignore align\n  a)\n";
+    assert_eq!(format_with(src, &aligned()), src);
+
+    let src = "(let [a 1\n      bbb 2]\n  a) ; lispfmt-ignore align\n";
+    assert_eq!(format_with(src, &aligned()), src);
+}
+
+#[test]
+fn ignore_comments_without_a_form_are_reported() {
+    let src = "(do ; lispfmt-ignore align\n  (let [a 1\n        bbb 2]\n    a)\n  ;; lispfmt-ignore\n  )\n";
+    let tree = parse(src).unwrap();
+    let reported = warnings(&tree)
+        .into_iter()
+        .map(|w| (w.code, w.message))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reported,
+        [
+            (
+                ErrorCode::UnusedDirective,
+                "`lispfmt-ignore` has no effect on `do`".to_string()
+            ),
+            (
+                ErrorCode::UnusedDirective,
+                "`lispfmt-ignore` is not followed by an expression".to_string()
+            ),
+        ]
+    );
+
+    let src = ";; lispfmt-ignore align\n(let [a 1\n      bbb 2]\n  a) ; lispfmt-ignore\n";
+    assert!(warnings(&parse(src).unwrap()).is_empty());
+}
+
+#[test]
+fn alignment_is_off_by_default() {
+    let src = "{:a 1\n :bbbb 2 ; c\n :cc 3}\n";
+    assert_eq!(format(src), src);
+}
+
+fn aligned_comments() -> Config {
+    Config {
+        align_comments: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn trailing_comments_of_consecutive_lines_are_aligned() {
+    let src = "(defn f [x] ; the function\n  (* x x)) ; square\n\n(foo) ; a\n";
+    let formatted = "(defn f [x] ; the function\n  (* x x))  ; square\n\n(foo) ; a\n";
+    assert_eq!(format_with(src, &aligned_comments()), formatted);
+}
+
+#[test]
+fn trailing_comments_start_at_the_minimum_column() {
+    let config = Config {
+        comment_min_column: 12,
+        ..aligned_comments()
+    };
+    let src = "(foo) ; a\n(bar baz) ; b\n";
+    let formatted = "(foo)       ; a\n(bar baz)   ; b\n";
+    assert_eq!(format_with(src, &config), formatted);
+}
+
+#[test]
+fn trailing_comments_needing_too_much_padding_are_not_aligned() {
+    let config = Config {
+        comment_max_padding: 3,
+        ..aligned_comments()
+    };
+    let src = "(a) ; short\n(a-longer-expression) ; long\n";
+    assert_eq!(format_with(src, &config), src);
+}
+
+#[test]
+fn trailing_comments_past_the_line_width_are_not_aligned() {
+    let config = Config {
+        max_width: 30,
+        ..aligned_comments()
+    };
+    let src = "(a) ; short\n(a-longer-expression) ; a long comment\n";
+    assert_eq!(format_with(src, &config), src);
+}
+
+fn reflowed() -> Config {
+    Config {
+        reflow_comments: true,
+        max_width: 30,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn comment_paragraphs_are_reflowed_to_the_line_width() {
+    let src = ";; This is a long comment that\n;; was wrapped by hand\n;; at odd places.\n(foo)\n";
+    let formatted =
+        ";; This is a long comment that\n;; was wrapped by hand at odd\n;; places.\n(foo)\n";
+    assert_eq!(format_with(src, &reflowed()), formatted);
+}
+
+#[test]
+fn blank_comment_lines_list_items_code_and_urls_are_kept() {
+    let src = "\
+;; First
+;; paragraph.
+;;
+;; - a list item
+;; - another
+;;     (code example)
+;; https://example.com/a/very/long/url
+(foo)
+";
+    let formatted = "\
+;; First paragraph.
+;;
+;; - a list item
+;; - another
+;;     (code example)
+;; https://example.com/a/very/long/url
+(foo)
+";
+    assert_eq!(format_with(src, &reflowed()), formatted);
+}
+
+#[test]
+fn comments_with_different_semicolons_are_separate_paragraphs() {
+    let src = "(foo\n  ; inner\n  ; comment\n  ;; other\n  bar) ; trailing\n";
+    let formatted = "(foo\n  ; inner comment\n  ;; other\n  bar) ; trailing\n";
+    assert_eq!(format_with(src, &reflowed()), formatted);
+}
+
+#[test]
+fn ignored_expression_lines_move_with_their_first_line() {
+    let src = "(foo\n      ;; lispfmt-ignore\n      (bar   1\n           2)\n      baz)\n";
+    let formatted = "(foo\n  ;; lispfmt-ignore\n  (bar   1\n       2)\n  baz)\n";
+    assert_eq!(format(src), formatted);
+}
+
+#[test]
+fn ignored_expression_indented_with_tabs_keeps_its_visual_columns() {
+    let src = "(foo\n      ;; lispfmt-ignore\n      (bar   1\n\t   2)\n      baz)\n";
+    let formatted = "(foo\n  ;; lispfmt-ignore\n  (bar   1\n       2)\n  baz)\n";
+    assert_eq!(format(src), formatted);
+
+    let config = Config {
+        tab_width: 4,
+        ..Default::default()
+    };
+    let formatted = "(foo\n  ;; lispfmt-ignore\n  (bar   1\n   2)\n  baz)\n";
+    assert_eq!(format_with(src, &config), formatted);
+}
+
+#[test]
+fn strings_in_ignored_expressions_are_kept_byte_for_byte() {
+    let src = "(a\n    ;; lispfmt-ignore\n    {:a \"x\n  y\"   :b 2})\n";
+    let formatted = "(a\n  ;; lispfmt-ignore\n  {:a \"x\n  y\"   :b 2})\n";
+    assert_eq!(format(src), formatted);
+}
+
+#[test]
+fn crlf_input_round_trips() {
+    let src = "(foo\r\n  bar)\r\n\r\n(baz)\r\n";
+    assert_eq!(format(src), src);
+    assert_eq!(format("(foo\r\n      bar)   \r\n"), "(foo\r\n  bar)\r\n");
+}
+
+#[test]
+fn first_line_ending_decides_the_line_ending() {
+    assert_eq!(
+        format("(foo\r\nbar)\n(baz)\n"),
+        "(foo\r\n  bar)\r\n(baz)\r\n"
+    );
+    assert_eq!(format("(foo\nbar)\r\n(baz)\r\n"), "(foo\n  bar)\n(baz)\n");
+}
+
+#[test]
+fn lone_carriage_returns_are_kept_as_the_line_ending() {
+    assert_eq!(format("(foo\rbar)\r(baz)\r"), "(foo\r  bar)\r(baz)\r");
+    assert_eq!(format("(foo\rbar)\n(baz)\n"), "(foo\r  bar)\r(baz)\r");
+
+    let config = Config {
+        insert_final_newline: true,
+        ..Default::default()
+    };
+    assert_eq!(format_with("(foo)\r(bar)", &config), "(foo)\r(bar)\r");
+    assert_eq!(format_with("(foo)\r", &config), "(foo)\r");
+}
+
+#[test]
+fn line_endings_are_normalized_when_configured() {
+    let lf = Config {
+        end_of_line: EndOfLine::Lf,
+        ..Default::default()
+    };
+    let crlf = Config {
+        end_of_line: EndOfLine::Crlf,
+        ..Default::default()
+    };
+    assert_eq!(format_with("(foo\r\nbar)\r\n", &lf), "(foo\n  bar)\n");
+    assert_eq!(format_with("(foo\nbar)\n", &crlf), "(foo\r\n  bar)\r\n");
+}
+
+#[test]
+fn line_endings_inside_strings_are_kept() {
+    let crlf = Config {
+        end_of_line: EndOfLine::Crlf,
+        ..Default::default()
+    };
+    let src = "(def s \"a\nb\r\nc\")\n";
+    assert_eq!(format_with(src, &crlf), "(def s \"a\nb\r\nc\")\r\n");
+}
+
+fn tabs() -> Config {
+    Config {
+        indent_style: IndentStyle::Tabs,
+        tab_width: 4,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn indentation_uses_tabs_and_aligns_with_spaces() {
+    let src = "(defn f []\n  (let [a 1]\n    [b ; c\n     d]))\n";
+    let formatted = "(defn f []\n  (let [a 1]\n\t[b ; c\n\t d]))\n";
+    assert_eq!(format_with(src, &tabs()), formatted);
+}
+
+#[test]
+fn ignored_expressions_keep_their_indentation_with_tabs() {
+    let src = ";; lispfmt-ignore\n(def m [1 0\n        0 1])\n";
+    assert_eq!(format_with(src, &tabs()), src);
+}
+
+#[test]
+fn disabled_regions_keep_their_indentation_with_tabs() {
+    let src = "(foo\n    ; lispfmt-off\n    [1 0\n     0 1]\n    ; lispfmt-on\n    bar)\n";
+    let formatted = "(foo\n    ; lispfmt-off\n    [1 0\n     0 1]\n    ; lispfmt-on\n  bar)\n";
+    assert_eq!(format_with(src, &tabs()), formatted);
+}
+
+#[test]
+fn sequences_and_tables_use_their_own_indentation() {
+    let config = Config {
+        sequence_indent: 3,
+        table_indent: 2,
+        ..Default::default()
+    };
+    let src = "(foo [aaaa ; x\n bbbb] {:a 1 ; y\n :b 2})\n";
+    let formatted = "(foo [aaaa ; x\n        bbbb] {:a 1 ; y\n                :b 2})\n";
+    assert_eq!(format_with(src, &config), formatted);
+}
+
+#[test]
+fn final_newline_is_inserted_when_configured() {
+    let config = Config {
+        insert_final_newline: true,
+        ..Default::default()
+    };
+    assert_eq!(format_with("(a)", &config), "(a)\n");
+    assert_eq!(format_with("(a)\n", &config), "(a)\n");
+    assert_eq!(format_with("(a)\r\n(b)", &config), "(a)\r\n(b)\r\n");
+    assert_eq!(format("(a)"), "(a)");
+    assert_eq!(format("(a)\n"), "(a)\n");
+}
+
+#[test]
+fn blank_lines_between_top_level_forms_are_limited() {
+    let src = "(a)\n\n\n\n(b)\n";
+    let with_max = |max_blank_lines| Config {
+        max_blank_lines,
+        ..Default::default()
+    };
+    assert_eq!(format(src), "(a)\n\n(b)\n");
+    assert_eq!(format_with(src, &with_max(0)), "(a)\n(b)\n");
+    assert_eq!(format_with(src, &with_max(2)), "(a)\n\n\n(b)\n");
+}
+
+#[test]
+fn blank_lines_inside_forms_are_limited() {
+    let src = "(a\n b\n\n\n\n c)\n";
+    let with_max = |max_inner_blank_lines| Config {
+        max_inner_blank_lines,
+        ..Default::default()
+    };
+    assert_eq!(format(src), "(a\n  b\n\n  c)\n");
+    assert_eq!(format_with(src, &with_max(0)), "(a\n  b\n  c)\n");
+    assert_eq!(format_with(src, &with_max(2)), "(a\n  b\n\n\n  c)\n");
+}
+
+fn narrow(ambiguous_width: usize) -> Config {
+    Config {
+        max_width: 20,
+        ambiguous_width,
+        reflow_comments: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn wide_characters_count_as_two_columns() {
+    assert_eq!(
+        format_with("(f [\"abcdef\" \"b\"])\n", &narrow(1)),
+        "(f [\"abcdef\" \"b\"])\n"
+    );
+    assert_eq!(
+        format_with("(f [\"漢字漢字漢字\" \"b\"])\n", &narrow(1)),
+        "(f [\"漢字漢字漢字\"\n    \"b\"])\n"
+    );
+    assert_eq!(
+        format_with("(f [\"😀😀😀😀😀😀\" \"b\"])\n", &narrow(1)),
+        "(f [\"😀😀😀😀😀😀\"\n    \"b\"])\n"
+    );
+}
+
+#[test]
+fn comments_are_reflowed_by_display_width() {
+    let src = ";; ab ab ab ab ab ab\n(a)\n";
+    assert_eq!(format_with(src, &narrow(1)), src);
+
+    let src = ";; 漢字 漢字 漢字 漢字 漢字 漢字\n(a)\n";
+    let formatted = ";; 漢字 漢字 漢字\n;; 漢字 漢字 漢字\n(a)\n";
+    assert_eq!(format_with(src, &narrow(1)), formatted);
+}
+
+#[test]
+fn ambiguous_width_changes_the_layout() {
+    let src = "(f [\"①②③④⑤⑥\" \"b\"])\n";
+    assert_eq!(format_with(src, &narrow(1)), src);
+    assert_eq!(
+        format_with(src, &narrow(2)),
+        "(f [\"①②③④⑤⑥\"\n    \"b\"])\n"
+    );
+}
@@ -0,0 +1,55 @@
+use lispfmt::{config::Config, indent::indent_for_newline};
+
+/// Returns the indentation of a new line inserted at the `|` in `src`.
+fn indent_at(src: &str, config: &Config) -> Option<usize> {
+    let offset = src.find('|').expect("Source should contain a cursor.");
+    indent_for_newline(&src.replace('|', ""), offset, config)
+}
+
+fn indent(src: &str) -> Option<usize> {
+    indent_at(src, &Config::default())
+}
+
+#[test]
+fn top_level_is_not_indented() {
+    assert_eq!(indent("(foo bar)|"), Some(0));
+    assert_eq!(indent("|"), Some(0));
+}
+
+#[test]
+fn innermost_open_container_decides_the_indentation() {
+    assert_eq!(indent("(foo|"), Some(2));
+    assert_eq!(indent("(foo [a|"), Some(6));
+    assert_eq!(indent("(foo {:a 1|"), Some(6));
+    assert_eq!(indent("(foo\n  (bar baz)|"), Some(2));
+    assert_eq!(indent("(foo\n    (bar|"), Some(6));
+}
+
+#[test]
+fn prefixed_containers_are_open() {
+    assert_eq!(indent("'(foo|"), Some(3));
+    assert_eq!(indent("{:a [1|"), Some(5));
+}
+
+#[test]
+fn text_after_the_offset_is_ignored() {
+    assert_eq!(indent("(foo|)"), Some(2));
+}
+
+#[test]
+fn indentation_follows_the_configuration() {
+    let config = Config {
+        list_indent: 4,
+        sequence_indent: 2,
+        tab_width: 4,
+        ..Default::default()
+    };
+    assert_eq!(indent_at("(foo|", &config), Some(4));
+    assert_eq!(indent_at("[a|", &config), Some(2));
+    assert_eq!(indent_at("\t(foo|", &config), Some(8));
+}
+
+#[test]
+fn offset_inside_a_string_has_no_indentation() {
+    assert_eq!(indent("(foo \"bar|"), None);
+}
@@ -0,0 +1,52 @@
+use lispfmt::line_index::{Encoding, LineCol, LineIndex};
+
+// `é` is 2 bytes and 1 UTF-16 code unit, `𝄞` is 4 bytes and 2 UTF-16 code units
+const SRC: &str = "(a\r\n é𝄞 b)\rc";
+
+fn pos(line: usize, column: usize) -> LineCol {
+    LineCol { line, column }
+}
+
+#[test]
+fn offsets_are_converted_in_each_encoding() {
+    let index = LineIndex::new(SRC);
+    let b = SRC.find('b').unwrap();
+
+    assert_eq!(index.line_col(b, Encoding::Utf8), pos(1, 8));
+    assert_eq!(index.line_col(b, Encoding::Utf16), pos(1, 5));
+    assert_eq!(index.line_col(b, Encoding::Char), pos(1, 4));
+
+    assert_eq!(index.offset(pos(1, 8), Encoding::Utf8), b);
+    assert_eq!(index.offset(pos(1, 5), Encoding::Utf16), b);
+    assert_eq!(index.offset(pos(1, 4), Encoding::Char), b);
+}
+
+#[test]
+fn all_line_endings_start_a_line() {
+    let index = LineIndex::new(SRC);
+    assert_eq!(index.line_count(), 3);
+    assert_eq!(index.line_range(0), Some(0..2));
+    assert_eq!(index.line_range(2), Some(SRC.len() - 1..SRC.len()));
+    assert_eq!(index.line_col(SRC.len() - 1, Encoding::Char), pos(2, 0));
+}
+
+#[test]
+fn lines_past_the_end_have_no_range() {
+    let index = LineIndex::new(SRC);
+    assert_eq!(index.line_range(3), None);
+    assert_eq!(index.offset(pos(3, 0), Encoding::Utf16), SRC.len());
+}
+
+#[test]
+fn offsets_inside_a_character_are_clamped_to_its_start() {
+    let index = LineIndex::new(SRC);
+    let clef = SRC.find('𝄞').unwrap();
+    assert_eq!(index.line_col(clef + 2, Encoding::Utf16), pos(1, 2));
+    assert_eq!(index.line_col(SRC.len() + 10, Encoding::Char), pos(2, 1));
+}
+
+#[test]
+fn columns_past_the_end_of_a_line_are_clamped() {
+    let index = LineIndex::new(SRC);
+    assert_eq!(index.offset(pos(0, 10), Encoding::Char), 2);
+}
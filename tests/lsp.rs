@@ -0,0 +1,225 @@
+use std::{path::Path, thread};
+
+use lispfmt::{config::Config, lsp::serve};
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use serde_json::{Value, json};
+
+const URI: &str = "file:///test.clj";
+
+/// A client sending scripted requests to a server running on another thread.
+struct Client {
+    connection: Connection,
+    server: Option<thread::JoinHandle<()>>,
+    next_id: i32,
+}
+
+impl Client {
+    fn start() -> Self {
+        Self::start_with(|_| Some(Config::default()))
+    }
+
+    /// Starts a server resolving the configuration of documents with `resolve`.
+    fn start_with(resolve: impl FnMut(Option<&Path>) -> Option<Config> + Send + 'static) -> Self {
+        let (server, connection) = Connection::memory();
+        let server = thread::spawn(move || serve(&server, resolve).unwrap());
+        let mut client = Client {
+            connection,
+            server: Some(server),
+            next_id: 0,
+        };
+
+        client.request("initialize", json!({ "capabilities": {} }));
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Response {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        let request = Request::new(id.clone(), method.to_string(), params);
+        self.connection
+            .sender
+            .send(Message::Request(request))
+            .unwrap();
+
+        loop {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Response(response) if response.id == id => return response,
+                _ => (),
+            }
+        }
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        let notification = Notification::new(method.to_string(), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))
+            .unwrap();
+    }
+
+    /// Opens a document and returns the diagnostics published for it.
+    fn open(&self, text: &str) -> Value {
+        self.open_uri(URI, text)
+    }
+
+    fn open_uri(&self, uri: &str, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": "clojure", "version": 1, "text": text },
+            }),
+        );
+
+        loop {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Notification(notification)
+                    if notification.method == "textDocument/publishDiagnostics" =>
+                {
+                    return notification.params["diagnostics"].clone();
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn formatting(&mut self, uri: &str) -> Response {
+        self.request(
+            "textDocument/formatting",
+            json!({
+                "textDocument": { "uri": uri },
+                "options": { "tabSize": 2, "insertSpaces": true },
+            }),
+        )
+    }
+
+    fn on_type_formatting(&mut self, line: u32, character: u32) -> Response {
+        self.request(
+            "textDocument/onTypeFormatting",
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": line, "character": character },
+                "ch": "\n",
+                "options": { "tabSize": 2, "insertSpaces": true },
+            }),
+        )
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        if let Some(server) = self.server.take() {
+            server.join().unwrap();
+        }
+    }
+}
+
+#[test]
+fn formatting_replaces_the_document() {
+    let mut client = Client::start();
+    assert_eq!(client.open("(foo\nbar)"), json!([]));
+
+    let response = client.formatting(URI);
+    assert_eq!(
+        response.result,
+        Some(json!([{
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 1, "character": 4 },
+            },
+            "newText": "(foo\n  bar)",
+        }]))
+    );
+}
+
+#[test]
+fn range_formatting_only_edits_overlapping_expressions() {
+    let mut client = Client::start();
+    client.open("(a\nb)\n(c\nd)");
+
+    let response = client.request(
+        "textDocument/rangeFormatting",
+        json!({
+            "textDocument": { "uri": URI },
+            "range": {
+                "start": { "line": 2, "character": 0 },
+                "end": { "line": 2, "character": 1 },
+            },
+            "options": { "tabSize": 2, "insertSpaces": true },
+        }),
+    );
+    assert_eq!(
+        response.result,
+        Some(json!([{
+            "range": {
+                "start": { "line": 2, "character": 0 },
+                "end": { "line": 3, "character": 2 },
+            },
+            "newText": "(c\n  d)",
+        }]))
+    );
+}
+
+#[test]
+fn on_type_formatting_reindents_the_new_line() {
+    let mut client = Client::start();
+    client.open("(foo\nbar)");
+
+    let response = client.on_type_formatting(1, 0);
+    assert_eq!(
+        response.result,
+        Some(json!([{
+            "range": {
+                "start": { "line": 1, "character": 0 },
+                "end": { "line": 1, "character": 0 },
+            },
+            "newText": "  ",
+        }]))
+    );
+}
+
+#[test]
+fn on_type_formatting_past_the_last_line_is_ignored() {
+    let mut client = Client::start();
+    client.open("(foo\nbar)");
+
+    let response = client.on_type_formatting(5, 0);
+    assert_eq!(response.result, Some(json!([])));
+    assert!(response.error.is_none());
+}
+
+#[test]
+fn parse_errors_are_published() {
+    let client = Client::start();
+    let diagnostics = client.open("(foo");
+    assert_eq!(diagnostics[0]["code"], "LF001");
+    assert_eq!(diagnostics[0]["severity"], 1);
+}
+
+#[test]
+fn documents_are_formatted_with_the_configuration_of_their_path() {
+    let mut client = Client::start_with(|path| match path {
+        Some(path) if path.starts_with("/my project") => Some(Config {
+            list_indent: 4,
+            ..Default::default()
+        }),
+        Some(path) if path.starts_with("/broken") => None,
+        _ => Some(Config::default()),
+    });
+
+    let uris = [
+        "file:///my%20project/a.clj",
+        "file:///broken/b.clj",
+        "untitled:Untitled-1",
+    ];
+    for uri in uris {
+        client.open_uri(uri, "(foo\nbar)");
+    }
+
+    let new_text = |response: Response| response.result.unwrap()[0]["newText"].clone();
+    assert_eq!(new_text(client.formatting(uris[0])), "(foo\n    bar)");
+    assert_eq!(client.formatting(uris[1]).result, Some(Value::Null));
+    assert_eq!(new_text(client.formatting(uris[2])), "(foo\n  bar)");
+}
@@ -0,0 +1,85 @@
+use lispfmt::{
+    config::Config,
+    error::Error,
+    paredit::{Operation, paredit},
+};
+
+/// Applies `operation` at the `|` in `src` and returns the result with the new cursor marked.
+fn edit(src: &str, operation: Operation) -> Option<String> {
+    let cursor = src.find('|').expect("Source should contain a cursor.");
+    let src = src.replacen('|', "", 1);
+    let edit = paredit(&src, operation, cursor, &Config::default()).unwrap()?;
+
+    let mut edited = src.clone();
+    edited.replace_range(edit.edit.span.into_range(), &edit.edit.text);
+    edited.insert(edit.cursor, '|');
+    Some(edited)
+}
+
+#[test]
+fn slurp() {
+    assert_eq!(edit("(a |b) c", Operation::Slurp).unwrap(), "(a |b c)");
+    assert_eq!(edit("(a |b)", Operation::Slurp), None);
+}
+
+#[test]
+fn barf() {
+    assert_eq!(
+        edit("(f (a |b c))", Operation::Barf).unwrap(),
+        "(f (a |b) c)"
+    );
+}
+
+#[test]
+fn splice() {
+    assert_eq!(edit("(a (|b c))", Operation::Splice).unwrap(), "(a |b c)");
+}
+
+#[test]
+fn raise() {
+    assert_eq!(edit("(a (b |c))", Operation::Raise).unwrap(), "(a |c)");
+}
+
+#[test]
+fn wrap() {
+    assert_eq!(edit("(a |b)", Operation::Wrap).unwrap(), "(a (|b))");
+}
+
+#[test]
+fn split() {
+    assert_eq!(
+        edit("(f (a b| c))", Operation::Split).unwrap(),
+        "(f (a b)| (c))"
+    );
+}
+
+#[test]
+fn split_inside_a_token_keeps_it_in_the_first_container() {
+    assert_eq!(
+        edit("(f (a b|c d))", Operation::Split).unwrap(),
+        "(f (a bc)| (d))"
+    );
+}
+
+#[test]
+fn join() {
+    assert_eq!(edit("(a b) |(c d)", Operation::Join).unwrap(), "(a b| c d)");
+    assert_eq!(edit("(a b) |[c d]", Operation::Join), None);
+}
+
+#[test]
+fn transpose() {
+    assert_eq!(edit("(a b |c)", Operation::Transpose).unwrap(), "(a c b|)");
+}
+
+#[test]
+fn kill() {
+    assert_eq!(edit("(a |b c)", Operation::Kill).unwrap(), "(a |c)");
+}
+
+#[test]
+fn edits_that_separate_a_table_key_from_its_value_are_errors() {
+    let src = "{:a 1 :b 2}";
+    let result = paredit(src, Operation::Barf, 7, &Config::default());
+    assert!(matches!(result, Err(Error::InvalidEdit(_))));
+}
@@ -0,0 +1,71 @@
+use lispfmt::{
+    error::{Error, ParseError},
+    parinfer::{Mode, parinfer},
+};
+
+fn indent(src: &str) -> String {
+    parinfer(src, Mode::Indent).unwrap()
+}
+
+fn paren(src: &str) -> String {
+    parinfer(src, Mode::Paren).unwrap()
+}
+
+#[test]
+fn indent_mode_closes_forms_by_indentation() {
+    assert_eq!(indent("(foo\n  (bar\nbaz"), "(foo\n  (bar))\nbaz");
+    assert_eq!(indent("(foo [a\n  b\n(c"), "(foo [a]\n  b)\n(c)");
+}
+
+#[test]
+fn indent_mode_moves_closers_to_the_last_line_of_a_form() {
+    assert_eq!(indent("(foo\n  bar\n)"), "(foo\n  bar)\n");
+    assert_eq!(indent("(foo)\n  bar"), "(foo\n  bar)");
+}
+
+#[test]
+fn indent_mode_removes_the_whitespace_before_a_closer_trail() {
+    assert_eq!(indent("(foo bar )\nbaz"), "(foo bar)\nbaz");
+    assert_eq!(indent("(foo bar  ]) ; done\nbaz"), "(foo bar) ; done\nbaz");
+}
+
+#[test]
+fn indent_mode_removes_unmatched_closers() {
+    assert_eq!(indent("(foo] bar)"), "(foo bar)");
+}
+
+#[test]
+fn paren_mode_indents_lines_inside_their_form() {
+    assert_eq!(paren("(foo\nbar)"), "(foo\n bar)");
+    assert_eq!(paren("(foo [a\nb])"), "(foo [a\n      b])");
+}
+
+#[test]
+fn paren_mode_dedents_lines_after_a_closed_form() {
+    assert_eq!(paren("(foo)\n  bar"), "(foo)\nbar");
+}
+
+#[test]
+fn paren_mode_reports_unmatched_closers() {
+    let Err(Error::Parse(errors)) = parinfer("(foo\n bar)\n  baz)", Mode::Paren) else {
+        panic!("Unmatched closer should be an error.");
+    };
+    assert!(matches!(
+        errors[..],
+        [ParseError::UnexpectedClosingDelimiter { span, .. }] if span.into_range() == (16..17)
+    ));
+}
+
+#[test]
+fn paren_mode_reports_unclosed_forms() {
+    let Err(Error::Parse(errors)) = parinfer("(foo [a\n bar", Mode::Paren) else {
+        panic!("Unclosed forms should be an error.");
+    };
+    assert!(matches!(
+        errors[..],
+        [
+            ParseError::UnclosedDelimiter { span: inner, .. },
+            ParseError::UnclosedDelimiter { span: outer, .. },
+        ] if inner.into_range() == (5..6) && outer.into_range() == (0..1)
+    ));
+}
@@ -0,0 +1,47 @@
+use lispfmt::parser::parse;
+use serde_json::json;
+
+#[test]
+fn tree_outline_snapshot() {
+    let tree = parse("(foo [a] ; c\n  {:b \"s\"})\n").unwrap();
+    let expected = r#"Root@0..25
+  List@0..24
+    LParen@0..1 "("
+    Symbol@1..4 "foo"
+    Space@4..5 " "
+    Sequence@5..8
+      LBracket@5..6 "["
+      Symbol@6..7 "a"
+      RBracket@7..8 "]"
+    Space@8..9 " "
+    Comment@9..12 "; c"
+    Newline@12..13 "\n"
+    Space@13..15 "  "
+    Table@15..23
+      LBrace@15..16 "{"
+      Pair@16..22
+        Keyword@16..18 ":b"
+        Space@18..19 " "
+        String@19..22 "\"s\""
+      RBrace@22..23 "}"
+    RParen@23..24 ")"
+  Newline@24..25 "\n"
+"#;
+    assert_eq!(format!("{:?}\n", tree), expected);
+}
+
+#[test]
+fn tree_json_snapshot() {
+    let tree = parse("'a").unwrap();
+    let token = |kind: &str, start: usize, end: usize, text: &str| json!({ "kind": kind, "span": { "start": start, "end": end }, "text": text });
+    let expected = json!({
+        "kind": "Root",
+        "span": { "start": 0, "end": 2 },
+        "children": [{
+            "kind": "Prefixed",
+            "span": { "start": 0, "end": 2 },
+            "children": [token("Prefix", 0, 1, "'"), token("Symbol", 1, 2, "a")],
+        }],
+    });
+    assert_eq!(tree.to_json(), expected);
+}
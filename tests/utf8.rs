@@ -0,0 +1,68 @@
+use lispfmt::{
+    config::{Bom, Config},
+    format::format_text,
+    kind::SyntaxKind,
+    parser::parse,
+    utf8::{decode, decode_lossy},
+};
+
+#[test]
+fn invalid_sequences_are_restored() {
+    let bytes = b"(foo \xff\xfe\n  \"\xc3\")";
+    let decoded = decode_lossy(bytes);
+    assert_eq!(decoded.text, "(foo \u{fffd}\u{fffd}\n  \"\u{fffd}\")");
+
+    let formatted = decoded.text.replace("\n  ", " ");
+    assert_eq!(decoded.encode(&formatted), b"(foo \xff\xfe \"\xc3\")");
+}
+
+#[test]
+fn replacement_and_private_use_characters_in_the_input_are_kept() {
+    let src = "\u{fffd} \u{10ff00} \u{10ffff}";
+    let mut bytes = src.as_bytes().to_vec();
+    bytes.extend_from_slice(b" \x80 \xef\xbf\xbd");
+
+    let decoded = decode_lossy(&bytes);
+    assert_eq!(decoded.encode(&decoded.text), bytes);
+}
+
+#[test]
+fn first_invalid_byte_is_reported() {
+    let error = decode(b"(a \xff)".to_vec()).unwrap_err();
+    assert_eq!(error.offset, 3);
+    assert_eq!(error.text, "(a \u{fffd})");
+}
+
+#[test]
+fn byte_order_mark_is_kept_by_default() {
+    let config = Config {
+        bom: Bom::Keep,
+        ..Default::default()
+    };
+    let formatted = format_text("\u{feff}(foo\nbar)\n", &config).unwrap();
+    assert_eq!(formatted, "\u{feff}(foo\n  bar)\n");
+}
+
+#[test]
+fn byte_order_mark_is_stripped_when_configured() {
+    let config = Config {
+        bom: Bom::Strip,
+        ..Default::default()
+    };
+    let formatted = format_text("\u{feff}(foo\nbar)\n", &config).unwrap();
+    assert_eq!(formatted, "(foo\n  bar)\n");
+}
+
+#[test]
+fn byte_order_mark_is_not_part_of_the_first_symbol() {
+    let tree = parse("\u{feff}foo").unwrap();
+    let symbol = tree
+        .children()
+        .find(|e| *e.kind() == SyntaxKind::Symbol)
+        .unwrap();
+    assert_eq!(symbol.text(), "foo");
+    assert_eq!(
+        format_text("\u{feff}foo", &Config::default()).unwrap(),
+        "\u{feff}foo"
+    );
+}
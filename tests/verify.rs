@@ -0,0 +1,65 @@
+use lispfmt::{
+    config::Config,
+    error::Error,
+    error_code::ErrorCode,
+    verify::{VerifyError, check_equivalence, verify},
+};
+
+#[test]
+fn whitespace_changes_inside_strings_are_detected() {
+    let result = check_equivalence("(f \"a   b\")", "(f \"a b\")", &Config::default());
+    assert!(matches!(result, Err(VerifyError::TokensChanged { .. })));
+}
+
+#[test]
+fn whitespace_changes_inside_comments_and_docstrings_are_allowed() {
+    let config = Config {
+        docstrings: true,
+        ..Default::default()
+    };
+    let src = "(defn f\n  \"Docs\n     here.\"\n  [] 1) ;;   a   comment\n";
+    let formatted = "(defn f\n  \"Docs here.\"\n  [] 1) ; a comment\n";
+    assert!(check_equivalence(src, formatted, &config).is_ok());
+}
+
+#[test]
+fn invalid_source_is_an_error() {
+    let result = check_equivalence("(f", "(f)", &Config::default());
+    assert!(matches!(result, Err(VerifyError::InvalidSource(_))));
+}
+
+#[test]
+fn formatted_output_is_verified() {
+    let src = "(defn f [x]\n      (+ x   1))\n";
+    let formatted = "(defn f [x]\n  (+ x 1))\n";
+    assert!(verify(src, formatted, &Config::default()).is_ok());
+}
+
+#[test]
+fn changed_tokens_are_reported_at_both_spans() {
+    let formatted = "(f\n  \"a b\")";
+    let error = check_equivalence("(f \"a   b\")", formatted, &Config::default()).unwrap_err();
+    let diagnostic = error.diagnostic(formatted);
+    assert_eq!(diagnostic.span.into_range(), 3..10);
+    assert_eq!(diagnostic.related.len(), 1);
+    assert_eq!(diagnostic.related[0].span.into_range(), 5..10);
+    assert_eq!(diagnostic.related[0].text, formatted);
+}
+
+#[test]
+fn idempotency_diff_is_attached_as_a_note() {
+    let error = VerifyError::NotIdempotent {
+        diff: "-a\n+b\n".to_string(),
+    };
+    assert_eq!(error.diagnostic("a\n").note.as_deref(), Some("-a\n+b\n"));
+}
+
+#[test]
+fn internal_errors_are_not_reported_as_changed_code() {
+    let diagnostics = Error::Internal("the formatted output cannot be parsed").diagnostics();
+    assert_eq!(diagnostics[0].code, ErrorCode::Internal);
+    assert_eq!(
+        diagnostics[0].message,
+        "internal error: the formatted output cannot be parsed"
+    );
+}